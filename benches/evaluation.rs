@@ -0,0 +1,133 @@
+
+// Flag evaluation benchmarks. Run with `cargo bench`. The client is offline, so nothing here
+// touches the network; the flags come from a store populated by the feature store factory.
+
+#[macro_use]
+extern crate criterion;
+extern crate ldclient;
+#[macro_use]
+extern crate serde_json;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use criterion::{BenchmarkId, Criterion, Throughput};
+use serde_json::Value;
+
+use ldclient::client::LDClient;
+use ldclient::config::LDConfig;
+use ldclient::feature_store::FeatureStore;
+use ldclient::flag::FeatureFlag;
+use ldclient::user::LDUser;
+
+const FLAG_KEY: &str = "bench-flag";
+
+// A flag with the kinds of rules that are common in practice: a long list of targeted keys, a
+// regex, a semantic version comparison, and a percentage rollout for everyone else.
+fn bench_flag(key: &str) -> FeatureFlag {
+	let targeted: Vec<String> = (0..1000).map(|i| format!("targeted-{}", i)).collect();
+	serde_json::from_value(json!({
+		"key": key,
+		"version": 1,
+		"on": true,
+		"prerequisites": [],
+		"salt": "salt",
+		"targets": [{ "values": targeted, "variation": 1 }],
+		"rules": [
+			{ "clauses": [{ "attribute": "key", "op": "in", "values": targeted, "negate": false }], "variation": 1 },
+			{ "clauses": [{ "attribute": "email", "op": "matches", "values": [".*@example\\.com$"], "negate": false }], "variation": 2 },
+			{ "clauses": [{ "attribute": "appVersion", "op": "semVerGreaterThan", "values": ["2.0.0"], "negate": false }], "variation": 1 }
+		],
+		"fallthrough": { "rollout": { "variations": [
+			{ "variation": 0, "weight": 50000 },
+			{ "variation": 1, "weight": 50000 }
+		] } },
+		"offVariation": 0,
+		"variations": [false, true, true],
+		"deleted": false
+	})).unwrap()
+}
+
+fn populated_store() -> FeatureStore {
+	let mut flags = HashMap::new();
+	flags.insert(FLAG_KEY.to_owned(), bench_flag(FLAG_KEY));
+	// unrelated flags, so that lookups aren't in a trivially small map
+	for i in 0..100 {
+		let key = format!("other-flag-{}", i);
+		flags.insert(key.clone(), bench_flag(&key));
+	}
+	let store = FeatureStore::in_memory_store();
+	store.init(flags);
+	store
+}
+
+fn offline_client() -> LDClient {
+	let config = LDConfig::default()
+		.with_offline(true)
+		.with_feature_store_factory(&populated_store);
+	LDClient::new("sdk-key".to_owned(), config).unwrap()
+}
+
+// Users that fall through to the rollout, so that every evaluation checks every rule.
+fn users() -> Vec<LDUser> {
+	(0..100).map(|i| LDUser::builder(format!("user-{}", i))
+		.email(format!("user-{}@example.org", i))
+		.custom_string("appVersion", "1.5.0")
+		.build()).collect()
+}
+
+fn single_thread(c: &mut Criterion) {
+	let client = offline_client();
+	let users = users();
+	let flag_key = FLAG_KEY.to_owned();
+	let mut i = 0;
+	c.bench_function("variation", |b| b.iter(|| {
+		i = (i + 1) % users.len();
+		client.variation(&flag_key, &users[i], Value::Bool(false))
+	}));
+
+	let store = populated_store();
+	c.bench_function("store get", |b| b.iter(|| store.get(&flag_key)));
+}
+
+// Evaluations per second with several threads sharing one client, which shows whether they
+// contend with each other.
+fn multi_thread(c: &mut Criterion) {
+	let client = Arc::new(offline_client());
+	let users = Arc::new(users());
+	let mut group = c.benchmark_group("variation threads");
+	group.throughput(Throughput::Elements(1));
+	for &threads in &[1u64, 2, 4, 8] {
+		group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, &threads| {
+			b.iter_custom(|iters| {
+				let per_thread = iters / threads + 1;
+				let started = Instant::now();
+				let handles: Vec<_> = (0..threads).map(|t| {
+					let client = client.clone();
+					let users = users.clone();
+					thread::spawn(move || {
+						let flag_key = FLAG_KEY.to_owned();
+						for i in 0..per_thread {
+							let user = &users[((i + t) as usize) % users.len()];
+							criterion::black_box(client.variation(&flag_key, user, Value::Bool(false)));
+						}
+					})
+				}).collect();
+				for handle in handles {
+					handle.join().unwrap();
+				}
+				started.elapsed()
+			})
+		});
+	}
+	group.finish();
+}
+
+criterion_group! {
+	name = benches;
+	config = Criterion::default().measurement_time(Duration::from_secs(5));
+	targets = single_thread, multi_thread
+}
+criterion_main!(benches);
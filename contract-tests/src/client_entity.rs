@@ -0,0 +1,256 @@
+
+use serde_json::{Map, Value};
+
+use ldclient::client::LDClient;
+use ldclient::config::LDConfig;
+use ldclient::context::LDContext;
+use ldclient::flags_state::FlagsStateOptions;
+use ldclient::user::LDUser;
+
+
+// The body of a request to create a client. Property names are fixed by the test harness.
+#[derive(Deserialize)]
+pub struct CreateInstanceParams {
+	pub configuration: SdkConfigParams
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+pub struct SdkConfigParams {
+	credential: String,
+	startWaitTimeMs: Option<u64>,
+	#[serde(default)]
+	initCanFail: bool,
+	polling: Option<PollingParams>,
+	// if omitted, the client must not send analytics events
+	events: Option<EventParams>,
+	tags: Option<TagParams>
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct PollingParams {
+	baseUri: Option<String>,
+	pollIntervalMs: Option<u64>
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct EventParams {
+	baseUri: Option<String>,
+	capacity: Option<usize>,
+	#[serde(default)]
+	enableDiagnostics: bool,
+	#[serde(default)]
+	allAttributesPrivate: bool,
+	#[serde(default)]
+	globalPrivateAttributes: Vec<String>,
+	flushIntervalMs: Option<u64>
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct TagParams {
+	applicationId: Option<String>,
+	applicationVersion: Option<String>
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+pub struct CommandParams {
+	command: String,
+	evaluate: Option<EvaluateParams>,
+	evaluateAll: Option<EvaluateAllParams>,
+	identifyEvent: Option<IdentifyEventParams>,
+	customEvent: Option<CustomEventParams>
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct EvaluateParams {
+	flagKey: String,
+	context: Option<Value>,
+	user: Option<Value>,
+	#[serde(default)]
+	defaultValue: Value,
+	#[serde(default)]
+	detail: bool
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct EvaluateAllParams {
+	context: Option<Value>,
+	user: Option<Value>,
+	#[serde(default)]
+	withReasons: bool,
+	#[serde(default)]
+	clientSideOnly: bool,
+	#[serde(default)]
+	detailsOnlyForTrackedFlags: bool
+}
+
+#[derive(Deserialize)]
+struct IdentifyEventParams {
+	context: Option<Value>,
+	user: Option<Value>
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct CustomEventParams {
+	eventKey: String,
+	context: Option<Value>,
+	user: Option<Value>,
+	data: Option<Value>,
+	metricValue: Option<f64>
+}
+
+pub struct ClientEntity {
+	client: LDClient
+}
+
+impl ClientEntity {
+	pub fn new(params: &SdkConfigParams) -> Result<ClientEntity, String> {
+		let mut config = LDConfig::default()
+			.with_sdk_key(params.credential.clone());
+		if let Some(millis) = params.startWaitTimeMs {
+			config = config.with_start_wait_millis(millis);
+		}
+		if let Some(ref polling) = params.polling {
+			if let Some(ref uri) = polling.baseUri {
+				config = config.with_base_uri(uri.clone());
+			}
+			if let Some(millis) = polling.pollIntervalMs {
+				config = config.with_polling_interval_millis(millis);
+			}
+		}
+		match params.events {
+			Some(ref events) => {
+				if let Some(ref uri) = events.baseUri {
+					config = config.with_events_uri(uri.clone());
+				}
+				if let Some(capacity) = events.capacity {
+					config = config.with_events_capacity(capacity);
+				}
+				if let Some(millis) = events.flushIntervalMs {
+					config = config.with_flush_interval_millis(millis);
+				}
+				config = config
+					.with_diagnostic_opt_out(!events.enableDiagnostics)
+					.with_all_attributes_private(events.allAttributesPrivate)
+					.with_private_attribute_names(events.globalPrivateAttributes.clone());
+			},
+			None => config = config.with_send_events(false)
+		}
+		if let Some(ref tags) = params.tags {
+			config = config.with_application_info(tags.applicationId.clone(), tags.applicationVersion.clone());
+		}
+
+		let client = LDClient::from_config(config)?;
+		let entity = ClientEntity { client };
+		if !entity.client.initialized() && !params.initCanFail {
+			entity.close();
+			return Err("client did not initialize within the start wait time".to_owned());
+		}
+		Ok(entity)
+	}
+
+	// Returns the JSON response for the command, or None if it doesn't have one.
+	pub fn do_command(&self, params: &CommandParams) -> Result<Option<Value>, String> {
+		match params.command.as_str() {
+			"evaluate" => {
+				let p = params.evaluate.as_ref().ok_or("missing evaluate parameters")?;
+				let context = context_from_params(&p.context, &p.user)?;
+				let detail = self.client.context_variation_detail(&p.flagKey, &context, p.defaultValue.clone());
+				if p.detail {
+					let mut result = json!({ "value": detail.value, "reason": detail.reason.to_json() });
+					if let Some(index) = detail.variation_index {
+						result["variationIndex"] = json!(index);
+					}
+					Ok(Some(result))
+				} else {
+					Ok(Some(json!({ "value": detail.value })))
+				}
+			},
+			"evaluateAll" => {
+				let p = params.evaluateAll.as_ref().ok_or("missing evaluateAll parameters")?;
+				let context = context_from_params(&p.context, &p.user)?;
+				let options = FlagsStateOptions::default()
+					.with_reasons(p.withReasons)
+					.with_client_side_only(p.clientSideOnly)
+					.with_details_only_for_tracked_flags(p.detailsOnlyForTrackedFlags);
+				let state = self.client.context_all_flags_state(&context, &options);
+				Ok(Some(json!({ "state": state.to_json() })))
+			},
+			"identifyEvent" => {
+				let p = params.identifyEvent.as_ref().ok_or("missing identifyEvent parameters")?;
+				self.client.context_identify(&context_from_params(&p.context, &p.user)?);
+				Ok(None)
+			},
+			"customEvent" => {
+				let p = params.customEvent.as_ref().ok_or("missing customEvent parameters")?;
+				let context = context_from_params(&p.context, &p.user)?;
+				self.client.context_track(&p.eventKey, &context, p.data.clone(), p.metricValue);
+				Ok(None)
+			},
+			"flushEvents" => {
+				self.client.flush();
+				Ok(None)
+			},
+			other => Err(format!("unknown command \"{}\"", other))
+		}
+	}
+
+	pub fn close(mut self) {
+		self.client.close();
+	}
+}
+
+// Commands give either a context, in the standard JSON representation, or an old-style user.
+fn context_from_params(context: &Option<Value>, user: &Option<Value>) -> Result<LDContext, String> {
+	match (context, user) {
+		(Some(c), _) => context_from_json(c),
+		(None, Some(u)) => {
+			let user: LDUser = serde_json::from_value(u.clone()).map_err(|e| format!("invalid user: {}", e))?;
+			Ok(LDContext::from(&user))
+		},
+		(None, None) => Err("command has no context or user".to_owned())
+	}
+}
+
+fn context_from_json(json: &Value) -> Result<LDContext, String> {
+	let obj = json.as_object().ok_or("context must be a JSON object")?;
+	match obj.get("kind").and_then(|k| k.as_str()) {
+		Some("multi") => {
+			let mut contexts = vec![];
+			for (kind, value) in obj.iter().filter(|&(name, _)| name != "kind") {
+				let single = value.as_object().ok_or("each context in a multi-kind context must be an object")?;
+				contexts.push(single_context_from_json(kind, single)?);
+			}
+			LDContext::multi(contexts)
+		},
+		Some(kind) => single_context_from_json(kind, obj),
+		None => Err("context has no kind".to_owned())
+	}
+}
+
+fn single_context_from_json(kind: &str, obj: &Map<String, Value>) -> Result<LDContext, String> {
+	let key = obj.get("key").and_then(|k| k.as_str()).ok_or("context has no key")?;
+	let mut builder = LDContext::builder(kind, key.to_owned());
+	for (name, value) in obj {
+		match name.as_str() {
+			"kind" | "key" => (),
+			"_meta" => {
+				let private = value.get("privateAttributes").and_then(|p| p.as_array());
+				for attr in private.into_iter().flatten().filter_map(|a| a.as_str()) {
+					builder = builder.private_attribute(attr);
+				}
+			},
+			// a null attribute is the same as one that isn't set
+			_ if value.is_null() => (),
+			_ => builder = builder.set(name, value.clone())
+		}
+	}
+	builder.build()
+}
@@ -0,0 +1,118 @@
+
+// A service that lets the shared SDK contract test harness drive LDClient over HTTP. The harness
+// creates clients pointed at its own mock polling and events endpoints, sends them commands
+// (evaluate a flag, send an event, etc.), and checks what comes back and what gets delivered.
+//
+// Run with `cargo run -p contract-tests`; it listens on port 8000 unless PORT is set.
+
+extern crate ldclient;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+extern crate tiny_http;
+
+mod client_entity;
+
+use std::collections::HashMap;
+use std::env;
+use std::io::Cursor;
+
+use serde_json::Value;
+use tiny_http::{Header, Method, Response, Server};
+
+use client_entity::{ClientEntity, CommandParams, CreateInstanceParams};
+
+// The optional parts of the contract that this SDK supports; the harness skips tests for the rest.
+const CAPABILITIES: &[&str] = &[
+	"server-side",
+	"server-side-polling",
+	"tags",
+	"all-flags-with-reasons",
+	"all-flags-client-side-only",
+	"all-flags-details-only-for-tracked-flags"
+];
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+fn main() {
+	let port = env::var("PORT").unwrap_or_else(|_| "8000".to_owned());
+	let server = Server::http(format!("0.0.0.0:{}", port))
+		.unwrap_or_else(|e| panic!("unable to listen on port {}: {}", port, e));
+	let mut clients: HashMap<String, ClientEntity> = HashMap::new();
+	let mut next_id: u64 = 1;
+
+	// Requests are handled one at a time; the harness never needs more than that.
+	for mut request in server.incoming_requests() {
+		let mut body = String::new();
+		if request.as_reader().read_to_string(&mut body).is_err() {
+			let _ = request.respond(status_response(400));
+			continue;
+		}
+		let method = request.method().clone();
+		let url = request.url().to_owned();
+		let client_id = url.trim_start_matches("/clients/").to_owned();
+		let response = match (method, url.as_str()) {
+			(Method::Get, "/") => json_response(200, &json!({ "capabilities": CAPABILITIES })),
+			(Method::Delete, "/") => {
+				let _ = request.respond(status_response(200));
+				return;
+			},
+			(Method::Post, "/") => {
+				match serde_json::from_str::<CreateInstanceParams>(&body)
+					.map_err(|e| format!("invalid parameters: {}", e))
+					.and_then(|params| ClientEntity::new(&params.configuration)) {
+					Ok(entity) => {
+						let id = next_id.to_string();
+						next_id += 1;
+						clients.insert(id.clone(), entity);
+						let location = Header::from_bytes("Location", format!("/clients/{}", id)).unwrap();
+						status_response(201).with_header(location)
+					},
+					Err(e) => error_response(500, &e)
+				}
+			},
+			(Method::Post, path) if path.starts_with("/clients/") => {
+				match clients.get(&client_id) {
+					Some(entity) => {
+						match serde_json::from_str::<CommandParams>(&body)
+							.map_err(|e| format!("invalid command: {}", e))
+							.and_then(|command| entity.do_command(&command)) {
+							Ok(Some(result)) => json_response(200, &result),
+							Ok(None) => status_response(202),
+							Err(e) => error_response(400, &e)
+						}
+					},
+					None => status_response(404)
+				}
+			},
+			(Method::Delete, path) if path.starts_with("/clients/") => {
+				match clients.remove(&client_id) {
+					Some(entity) => {
+						entity.close();
+						status_response(204)
+					},
+					None => status_response(404)
+				}
+			},
+			_ => status_response(404)
+		};
+		let _ = request.respond(response);
+	}
+}
+
+fn status_response(status: u16) -> HttpResponse {
+	Response::from_data(vec![]).with_status_code(status)
+}
+
+fn json_response(status: u16, body: &Value) -> HttpResponse {
+	let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+	Response::from_data(body.to_string().into_bytes())
+		.with_status_code(status)
+		.with_header(content_type)
+}
+
+fn error_response(status: u16, message: &str) -> HttpResponse {
+	Response::from_string(message).with_status_code(status)
+}
@@ -0,0 +1,63 @@
+
+// A reference to a context attribute, as used in clause "attribute" and rollout "bucketBy"
+// properties. A name that starts with '/' is a path into nested JSON objects, like
+// "/subscription/plan", where "~1" stands for a literal '/' and "~0" for a literal '~' within
+// a path component. Any other name refers to a top-level attribute with exactly that name.
+#[derive(Clone)]
+#[derive(Debug, PartialEq)]
+pub struct AttributeRef {
+	components: Vec<String>,
+	valid: bool
+}
+
+impl AttributeRef {
+
+	pub fn new(s: &str) -> AttributeRef {
+		if !s.starts_with('/') {
+			return AttributeRef { components: vec![s.to_owned()], valid: !s.is_empty() };
+		}
+		let mut components: Vec<String> = vec![];
+		for part in s[1..].split('/') {
+			match unescape(part) {
+				Some(c) => components.push(c),
+				None => return AttributeRef { components: vec![], valid: false }
+			}
+		}
+		AttributeRef { components, valid: true }
+	}
+
+	// An invalid reference, such as "/" or "/a//b", never matches anything.
+	pub fn is_valid(&self) -> bool {
+		self.valid
+	}
+
+	// The top-level attribute name, followed by the keys to look up in nested objects.
+	pub fn components(&self) -> &Vec<String> {
+		&self.components
+	}
+
+	// True if this refers to a single top-level attribute with the given name.
+	pub fn is_top_level(&self, name: &str) -> bool {
+		self.valid && self.components.len() == 1 && self.components[0] == name
+	}
+}
+
+fn unescape(part: &str) -> Option<String> {
+	if part.is_empty() {
+		return None;
+	}
+	let mut out = String::new();
+	let mut chars = part.chars();
+	while let Some(c) = chars.next() {
+		if c == '~' {
+			match chars.next() {
+				Some('0') => out.push('~'),
+				Some('1') => out.push('/'),
+				_ => return None
+			}
+		} else {
+			out.push(c);
+		}
+	}
+	Some(out)
+}
@@ -1,61 +1,195 @@
 
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use std::time::Duration;
 
+use hmac::{Hmac, Mac};
 use serde_json::Value;
+use sha2::Sha256;
 
 use config::LDConfig;
+use context::LDContext;
+use diagnostics::DiagnosticsRecorder;
+use evaluation::{EvaluationDetail, EvaluationReason, ErrorKind};
+use event;
+use event::Event;
+use event_processor::EventProcessor;
 use feature_store::FeatureStore;
+use flags_state::{FeatureFlagsState, FlagsStateOptions};
 use polling::PollingProcessor;
+use requestor::make_http_client;
 use user::LDUser;
 
 
 pub struct LDClient {
+	sdk_key: String,
 	poller: PollingProcessor,
-	store: Arc<Mutex<FeatureStore>>
+	events: EventProcessor,
+	store: Arc<FeatureStore>
 }
 
 impl LDClient {
-	pub fn new(sdk_key: String, config: LDConfig) -> LDClient {
+	// Fails only if the configuration is invalid, e.g. a malformed proxy URL or certificate.
+	pub fn new(sdk_key: String, config: LDConfig) -> Result<LDClient, String> {
+		let http_client = make_http_client(&config)?;
 		let store_impl = (*config.feature_store_factory)();
-		let store = Arc::new(Mutex::new(store_impl));
+		let store = Arc::new(store_impl);
+		let diagnostics = Arc::new(DiagnosticsRecorder::new());
+		let events = EventProcessor::new(sdk_key.clone(), &config, http_client.clone(), &diagnostics);
 		let poller: PollingProcessor =
-			PollingProcessor::new(sdk_key, &store, &config.base_uri, config.polling_interval_millis);
+			PollingProcessor::new(sdk_key.clone(), &store, &config.base_uri, config.polling_interval_millis,
+				http_client, &diagnostics);
 		let mut client = LDClient {
-			poller: poller,
-			store: store
+			sdk_key,
+			poller,
+			events,
+			store
 		};
-		client.start();
-		client
+		if !config.offline {
+			client.start(config.start_wait_millis);
+		}
+		Ok(client)
 	}
 
-	fn start(&mut self) {
+	// Like new, but takes the SDK key from the configuration, e.g. from LD_SDK_KEY when the
+	// configuration comes from LDConfig::from_env.
+	pub fn from_config(config: LDConfig) -> Result<LDClient, String> {
+		match config.sdk_key.clone() {
+			Some(sdk_key) => LDClient::new(sdk_key, config),
+			None => Err("the configuration has no SDK key".to_owned())
+		}
+	}
+
+	fn start(&mut self, wait_millis: u64) {
 		let ready = self.poller.start();
-		match ready.recv() {
-			Ok(_) => (),
-			Err(_) => () // TODO: error logging
+		if ready.recv_timeout(Duration::from_millis(wait_millis)).is_err() {
+			// TODO: error logging
 		}
 	}
 
+	// True once flags have been received. Until then, every evaluation returns the default value.
+	pub fn initialized(&self) -> bool {
+		self.store.initialized()
+	}
+
 	pub fn variation(&self, flag_key: &String, user: &LDUser, default: Value) -> Value {
-		let store = self.store.lock().unwrap();
-		match store.get(flag_key) {
+		self.context_variation(flag_key, &LDContext::from(user), default)
+	}
+
+	// Like variation, but for any kind of context, including multi-kind contexts.
+	pub fn context_variation(&self, flag_key: &String, context: &LDContext, default: Value) -> Value {
+		self.context_variation_detail(flag_key, context, default).value
+	}
+
+	// Like variation, but also says why the value was chosen.
+	pub fn variation_detail(&self, flag_key: &String, user: &LDUser, default: Value) -> EvaluationDetail {
+		self.context_variation_detail(flag_key, &LDContext::from(user), default)
+	}
+
+	pub fn context_variation_detail(&self, flag_key: &String, context: &LDContext, default: Value) -> EvaluationDetail {
+		match self.store.get(flag_key) {
 			Some(flag) => {
-				let eval_result = flag.evaluate(user, &store);
-				// TODO: send events (eval_result.1)
-				eval_result.0
+				let (mut detail, prereq_events) = flag.evaluate(context, &self.store);
+				// an error, or an off flag with no off variation, gives the caller's default
+				if detail.variation_index.is_none() {
+					detail.value = default.clone();
+				}
+				for prereq_event in prereq_events {
+					self.events.send(Event::FeatureRequest(prereq_event));
+				}
+				self.events.send(Event::FeatureRequest(
+					event::feature_request(&flag, context, &detail, Some(default), None)));
+				detail
+			}
+			None => {
+				self.events.send(Event::FeatureRequest(
+					event::unknown_feature_request(flag_key, context, default.clone())));
+				EvaluationDetail {
+					value: default,
+					variation_index: None,
+					reason: EvaluationReason::Error { error_kind: ErrorKind::FlagNotFound }
+				}
 			}
-			None => default
 		}
 	}
 
 	pub fn all_flags(&self, user: &LDUser) -> HashMap<String, Value> {
-		let store = self.store.lock().unwrap();
-		let flags = store.all();
+		self.context_all_flags(&LDContext::from(user))
+	}
+
+	pub fn context_all_flags(&self, context: &LDContext) -> HashMap<String, Value> {
+		let flags = self.store.all();
 		let mut ret: HashMap<String, Value> = HashMap::new();
 		for (key, flag) in flags.iter() {
-			ret.insert(key.clone(), flag.evaluate(user, &store).0);
+			ret.insert(key.clone(), flag.evaluate(context, &self.store).0.value);
 		}
 		ret
 	}
+
+	// Evaluates every flag for the user, with the metadata that a client-side SDK needs. This
+	// is meant for bootstrapping the JavaScript SDK, so no analytics events are sent.
+	pub fn all_flags_state(&self, user: &LDUser, options: &FlagsStateOptions) -> FeatureFlagsState {
+		self.context_all_flags_state(&LDContext::from(user), options)
+	}
+
+	pub fn context_all_flags_state(&self, context: &LDContext, options: &FlagsStateOptions) -> FeatureFlagsState {
+		if !self.store.initialized() {
+			return FeatureFlagsState::invalid();
+		}
+		let mut state = FeatureFlagsState::new();
+		for flag in self.store.all().values() {
+			if flag.deleted || (options.client_side_only && !flag.is_client_side()) {
+				continue;
+			}
+			let (detail, _prereq_events) = flag.evaluate(context, &self.store);
+			state.add_flag(flag, detail, options);
+		}
+		state
+	}
+
+	// The hash that the JavaScript SDK must be given, along with the user, when secure mode is
+	// enabled. It proves that the user came from this server, because only the server knows the
+	// SDK key.
+	pub fn secure_mode_hash(&self, user: &LDUser) -> String {
+		self.context_secure_mode_hash(&LDContext::from(user))
+	}
+
+	// For a multi-kind context, or any kind other than "user", the hash is of the canonical key.
+	pub fn context_secure_mode_hash(&self, context: &LDContext) -> String {
+		// HMAC accepts a key of any length, so this can't fail
+		let mut mac = Hmac::<Sha256>::new_from_slice(self.sdk_key.as_bytes()).unwrap();
+		mac.update(context.canonical_key().as_bytes());
+		mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+	}
+
+	// Tells LaunchDarkly about a user, so that it shows up on the dashboard even if no flags
+	// have been evaluated for it.
+	pub fn identify(&self, user: &LDUser) {
+		self.context_identify(&LDContext::from(user));
+	}
+
+	pub fn context_identify(&self, context: &LDContext) {
+		self.events.send(event::identify(context));
+	}
+
+	// Records a custom event, such as a conversion, that can be used as a metric in experiments.
+	pub fn track(&self, event_key: &str, user: &LDUser, data: Option<Value>, metric_value: Option<f64>) {
+		self.context_track(event_key, &LDContext::from(user), data, metric_value);
+	}
+
+	pub fn context_track(&self, event_key: &str, context: &LDContext, data: Option<Value>, metric_value: Option<f64>) {
+		self.events.send(event::custom(event_key, context, data, metric_value));
+	}
+
+	// Delivers any pending analytics events in the background.
+	pub fn flush(&self) {
+		self.events.flush();
+	}
+
+	// Stops polling and delivers any pending analytics events. The client keeps evaluating flags
+	// from the last data it received, but no further events are sent.
+	pub fn close(&mut self) {
+		self.poller.stop();
+		self.events.close();
+	}
 }
@@ -1,46 +1,427 @@
 
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use serde_json;
+use toml;
+
 use feature_store::FeatureStore;
 
 // TODO: The feature store should be part of the configuration, but I haven't figured out
 // how to mark things with the proper "lifetime" specifier to keep Rust happy when the store
 // is referenced elsewhere.
 
+#[derive(Clone)]
 pub struct LDConfig {
+	pub sdk_key: Option<String>,
 	pub base_uri: String,
+	pub stream_uri: String,
+	pub events_uri: String,
+	pub offline: bool,
 	pub polling_interval_millis: u64,
-	pub feature_store_factory: &'static Fn() -> FeatureStore
+	// How long LDClient::new waits for flags to be received before returning anyway, in which
+	// case evaluations return default values until the first successful poll.
+	pub start_wait_millis: u64,
+	// Analytics events are buffered, up to events_capacity of them, and sent every
+	// flush_interval_millis. Setting send_events to false discards them instead.
+	pub send_events: bool,
+	pub events_capacity: usize,
+	pub flush_interval_millis: u64,
+	// Users are sent in full only once per user_keys_flush_interval_millis, remembering at most
+	// user_keys_capacity of them; other events just refer to them by key.
+	pub user_keys_capacity: usize,
+	pub user_keys_flush_interval_millis: u64,
+	// Diagnostic events describe the SDK's configuration and performance to LaunchDarkly, and
+	// are sent every diagnostic_recording_interval_millis (at least a minute) unless opted out.
+	pub diagnostic_opt_out: bool,
+	pub diagnostic_recording_interval_millis: u64,
+	pub http: HttpConfig,
+	// Identifies the application using the SDK; sent in the X-LaunchDarkly-Tags header. Values
+	// other than 1-64 letters, digits, '.', '-' and '_' are left out.
+	pub application_id: Option<String>,
+	pub application_version: Option<String>,
+	// Set by libraries that wrap this SDK; sent in the X-LaunchDarkly-Wrapper header.
+	pub wrapper_name: Option<String>,
+	pub wrapper_version: Option<String>,
+	// Attributes to leave out of analytics events; see UserFilter.
+	pub all_attributes_private: bool,
+	pub private_attribute_names: Vec<String>,
+	pub feature_store_factory: &'static dyn Fn() -> FeatureStore
 }
 
-impl LDConfig {
-	pub fn default() -> LDConfig {
+// Network settings shared by every component that talks to LaunchDarkly (polling, streaming
+// and events), so that they all go through the same proxy and trust the same certificates.
+#[derive(Clone)]
+pub struct HttpConfig {
+	pub proxy: Option<ProxyConfig>,
+	// Extra trusted root certificates, in PEM format, added to the system's defaults.
+	pub root_certificates: Vec<Vec<u8>>,
+	pub connect_timeout_millis: u64,
+	// The longest a whole request may take, from connecting until the response has been read.
+	pub request_timeout_millis: u64,
+	// Additional headers sent with every request.
+	pub headers: Vec<(String, String)>
+}
+
+#[derive(Clone)]
+pub struct ProxyConfig {
+	pub url: String,
+	pub username: Option<String>,
+	pub password: Option<String>
+}
+
+// The settings that can be read from a config file. Anything that is omitted keeps its default;
+// anything unrecognized, such as a misspelled name, is an error.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+	sdk_key: Option<String>,
+	base_uri: Option<String>,
+	stream_uri: Option<String>,
+	events_uri: Option<String>,
+	offline: Option<bool>,
+	polling_interval_millis: Option<u64>,
+	start_wait_millis: Option<u64>,
+	send_events: Option<bool>,
+	events_capacity: Option<usize>,
+	flush_interval_millis: Option<u64>,
+	user_keys_capacity: Option<usize>,
+	user_keys_flush_interval_millis: Option<u64>,
+	diagnostic_opt_out: Option<bool>,
+	diagnostic_recording_interval_millis: Option<u64>,
+	proxy_url: Option<String>,
+	proxy_username: Option<String>,
+	proxy_password: Option<String>,
+	connect_timeout_millis: Option<u64>,
+	request_timeout_millis: Option<u64>,
+	application_id: Option<String>,
+	application_version: Option<String>,
+	all_attributes_private: Option<bool>,
+	private_attribute_names: Option<Vec<String>>
+}
+
+impl Default for LDConfig {
+	fn default() -> LDConfig {
 		LDConfig {
+			sdk_key: None,
 			base_uri: String::from("https://app.launchdarkly.com"),
+			stream_uri: String::from("https://stream.launchdarkly.com"),
+			events_uri: String::from("https://events.launchdarkly.com"),
+			offline: false,
 			polling_interval_millis: 30000,
+			start_wait_millis: 5000,
+			send_events: true,
+			events_capacity: 10000,
+			flush_interval_millis: 5000,
+			user_keys_capacity: 1000,
+			user_keys_flush_interval_millis: 300000,
+			diagnostic_opt_out: false,
+			diagnostic_recording_interval_millis: 900000,
+			http: HttpConfig::default(),
+			application_id: None,
+			application_version: None,
+			wrapper_name: None,
+			wrapper_version: None,
+			all_attributes_private: false,
+			private_attribute_names: vec![],
 			feature_store_factory: &FeatureStore::in_memory_store
 		}
 	}
+}
 
-	pub fn with_base_uri(&self, base_uri: String) -> LDConfig {
-		LDConfig {
-			base_uri: base_uri.clone(),
-			polling_interval_millis: self.polling_interval_millis,
-			feature_store_factory: self.feature_store_factory
+impl LDConfig {
+	// Builds a configuration from LD_* environment variables. Variables that are not set keep
+	// their default values; variables that are set but can't be parsed are an error.
+	pub fn from_env() -> Result<LDConfig, String> {
+		let mut config = LDConfig::default();
+		if let Some(s) = env_var("LD_SDK_KEY")? {
+			config.sdk_key = Some(s);
+		}
+		if let Some(s) = env_var("LD_BASE_URI")? {
+			config.base_uri = s;
+		}
+		if let Some(s) = env_var("LD_STREAM_URI")? {
+			config.stream_uri = s;
+		}
+		if let Some(s) = env_var("LD_EVENTS_URI")? {
+			config.events_uri = s;
+		}
+		if let Some(s) = env_var("LD_OFFLINE")? {
+			config.offline = parse_bool("LD_OFFLINE", &s)?;
+		}
+		if let Some(s) = env_var("LD_POLL_INTERVAL")? {
+			config.polling_interval_millis = parse_millis("LD_POLL_INTERVAL", &s)?;
+		}
+		if let Some(s) = env_var("LD_START_WAIT")? {
+			config.start_wait_millis = parse_millis("LD_START_WAIT", &s)?;
+		}
+		if let Some(s) = env_var("LD_SEND_EVENTS")? {
+			config.send_events = parse_bool("LD_SEND_EVENTS", &s)?;
+		}
+		if let Some(s) = env_var("LD_EVENTS_CAPACITY")? {
+			config.events_capacity = parse_count("LD_EVENTS_CAPACITY", &s)?;
+		}
+		if let Some(s) = env_var("LD_FLUSH_INTERVAL")? {
+			config.flush_interval_millis = parse_millis("LD_FLUSH_INTERVAL", &s)?;
+		}
+		if let Some(s) = env_var("LD_USER_KEYS_CAPACITY")? {
+			config.user_keys_capacity = parse_count("LD_USER_KEYS_CAPACITY", &s)?;
+		}
+		if let Some(s) = env_var("LD_USER_KEYS_FLUSH_INTERVAL")? {
+			config.user_keys_flush_interval_millis = parse_millis("LD_USER_KEYS_FLUSH_INTERVAL", &s)?;
+		}
+		if let Some(s) = env_var("LD_DIAGNOSTIC_OPT_OUT")? {
+			config.diagnostic_opt_out = parse_bool("LD_DIAGNOSTIC_OPT_OUT", &s)?;
+		}
+		if let Some(s) = env_var("LD_DIAGNOSTIC_RECORDING_INTERVAL")? {
+			config.diagnostic_recording_interval_millis = parse_millis("LD_DIAGNOSTIC_RECORDING_INTERVAL", &s)?;
+		}
+		if let Some(s) = env_var("LD_PROXY_URL")? {
+			config.http = config.http.with_proxy(s);
 		}
+		if let Some(s) = env_var("LD_PROXY_USERNAME")? {
+			let password = env_var("LD_PROXY_PASSWORD")?.unwrap_or_default();
+			config.http = config.http.with_proxy_auth(s, password)?;
+		}
+		if let Some(s) = env_var("LD_CONNECT_TIMEOUT")? {
+			config.http.connect_timeout_millis = parse_millis("LD_CONNECT_TIMEOUT", &s)?;
+		}
+		if let Some(s) = env_var("LD_REQUEST_TIMEOUT")? {
+			config.http.request_timeout_millis = parse_millis("LD_REQUEST_TIMEOUT", &s)?;
+		}
+		if let Some(s) = env_var("LD_APPLICATION_ID")? {
+			config.application_id = Some(s);
+		}
+		if let Some(s) = env_var("LD_APPLICATION_VERSION")? {
+			config.application_version = Some(s);
+		}
+		if let Some(s) = env_var("LD_ALL_ATTRIBUTES_PRIVATE")? {
+			config.all_attributes_private = parse_bool("LD_ALL_ATTRIBUTES_PRIVATE", &s)?;
+		}
+		if let Some(s) = env_var("LD_PRIVATE_ATTRIBUTE_NAMES")? {
+			config.private_attribute_names = s.split(',')
+				.map(|name| name.trim().to_owned())
+				.filter(|name| !name.is_empty())
+				.collect();
+		}
+		Ok(config)
+	}
+
+	// Reads a configuration file. Files ending in ".json" are parsed as JSON; anything else is
+	// parsed as TOML. Property names are the same as the LDConfig field names.
+	pub fn from_file<P: AsRef<Path>>(path: P) -> Result<LDConfig, String> {
+		let path = path.as_ref();
+		let mut content = String::new();
+		File::open(path)
+			.and_then(|mut f| f.read_to_string(&mut content))
+			.map_err(|e| format!("unable to read config file {}: {}", path.display(), e))?;
+		let is_json = path.extension().and_then(|ext| ext.to_str()) == Some("json");
+		let parsed: ConfigFile = if is_json {
+			serde_json::from_str(&content)
+				.map_err(|e| format!("invalid JSON in config file {}: {}", path.display(), e))?
+		} else {
+			toml::from_str(&content)
+				.map_err(|e| format!("invalid TOML in config file {}: {}", path.display(), e))?
+		};
+		let defaults = LDConfig::default();
+		let mut http = defaults.http.clone();
+		if let Some(url) = parsed.proxy_url {
+			http = http.with_proxy(url);
+		}
+		if let Some(username) = parsed.proxy_username {
+			http = http.with_proxy_auth(username, parsed.proxy_password.unwrap_or_default())?;
+		}
+		if let Some(millis) = parsed.connect_timeout_millis {
+			http.connect_timeout_millis = millis;
+		}
+		if let Some(millis) = parsed.request_timeout_millis {
+			http.request_timeout_millis = millis;
+		}
+		Ok(LDConfig {
+			sdk_key: parsed.sdk_key,
+			base_uri: parsed.base_uri.unwrap_or(defaults.base_uri),
+			stream_uri: parsed.stream_uri.unwrap_or(defaults.stream_uri),
+			events_uri: parsed.events_uri.unwrap_or(defaults.events_uri),
+			offline: parsed.offline.unwrap_or(defaults.offline),
+			polling_interval_millis: parsed.polling_interval_millis.unwrap_or(defaults.polling_interval_millis),
+			start_wait_millis: parsed.start_wait_millis.unwrap_or(defaults.start_wait_millis),
+			send_events: parsed.send_events.unwrap_or(defaults.send_events),
+			events_capacity: parsed.events_capacity.unwrap_or(defaults.events_capacity),
+			flush_interval_millis: parsed.flush_interval_millis.unwrap_or(defaults.flush_interval_millis),
+			user_keys_capacity: parsed.user_keys_capacity.unwrap_or(defaults.user_keys_capacity),
+			user_keys_flush_interval_millis:
+				parsed.user_keys_flush_interval_millis.unwrap_or(defaults.user_keys_flush_interval_millis),
+			diagnostic_opt_out: parsed.diagnostic_opt_out.unwrap_or(defaults.diagnostic_opt_out),
+			diagnostic_recording_interval_millis: parsed.diagnostic_recording_interval_millis
+				.unwrap_or(defaults.diagnostic_recording_interval_millis),
+			http,
+			application_id: parsed.application_id,
+			application_version: parsed.application_version,
+			wrapper_name: None,
+			wrapper_version: None,
+			all_attributes_private: parsed.all_attributes_private.unwrap_or(defaults.all_attributes_private),
+			private_attribute_names: parsed.private_attribute_names.unwrap_or(defaults.private_attribute_names),
+			feature_store_factory: defaults.feature_store_factory
+		})
+	}
+
+	pub fn with_sdk_key(&self, sdk_key: String) -> LDConfig {
+		LDConfig { sdk_key: Some(sdk_key), ..self.clone() }
+	}
+
+	pub fn with_base_uri(&self, base_uri: String) -> LDConfig {
+		LDConfig { base_uri, ..self.clone() }
+	}
+
+	pub fn with_stream_uri(&self, stream_uri: String) -> LDConfig {
+		LDConfig { stream_uri, ..self.clone() }
+	}
+
+	pub fn with_events_uri(&self, events_uri: String) -> LDConfig {
+		LDConfig { events_uri, ..self.clone() }
+	}
+
+	pub fn with_offline(&self, offline: bool) -> LDConfig {
+		LDConfig { offline, ..self.clone() }
 	}
 
 	pub fn with_polling_interval_millis(&self, millis: u64) -> LDConfig {
-		LDConfig {
-			base_uri: self.base_uri.clone(),
-			polling_interval_millis: millis,
-			feature_store_factory: self.feature_store_factory
+		LDConfig { polling_interval_millis: millis, ..self.clone() }
+	}
+
+	pub fn with_start_wait_millis(&self, millis: u64) -> LDConfig {
+		LDConfig { start_wait_millis: millis, ..self.clone() }
+	}
+
+	pub fn with_send_events(&self, send_events: bool) -> LDConfig {
+		LDConfig { send_events, ..self.clone() }
+	}
+
+	pub fn with_events_capacity(&self, capacity: usize) -> LDConfig {
+		LDConfig { events_capacity: capacity, ..self.clone() }
+	}
+
+	pub fn with_flush_interval_millis(&self, millis: u64) -> LDConfig {
+		LDConfig { flush_interval_millis: millis, ..self.clone() }
+	}
+
+	pub fn with_user_keys_capacity(&self, capacity: usize) -> LDConfig {
+		LDConfig { user_keys_capacity: capacity, ..self.clone() }
+	}
+
+	pub fn with_user_keys_flush_interval_millis(&self, millis: u64) -> LDConfig {
+		LDConfig { user_keys_flush_interval_millis: millis, ..self.clone() }
+	}
+
+	pub fn with_diagnostic_opt_out(&self, opt_out: bool) -> LDConfig {
+		LDConfig { diagnostic_opt_out: opt_out, ..self.clone() }
+	}
+
+	pub fn with_diagnostic_recording_interval_millis(&self, millis: u64) -> LDConfig {
+		LDConfig { diagnostic_recording_interval_millis: millis, ..self.clone() }
+	}
+
+	pub fn with_http(&self, http: HttpConfig) -> LDConfig {
+		LDConfig { http, ..self.clone() }
+	}
+
+	pub fn with_application_info(&self, id: Option<String>, version: Option<String>) -> LDConfig {
+		LDConfig { application_id: id, application_version: version, ..self.clone() }
+	}
+
+	pub fn with_wrapper(&self, name: String, version: Option<String>) -> LDConfig {
+		LDConfig { wrapper_name: Some(name), wrapper_version: version, ..self.clone() }
+	}
+
+	pub fn with_all_attributes_private(&self, value: bool) -> LDConfig {
+		LDConfig { all_attributes_private: value, ..self.clone() }
+	}
+
+	pub fn with_private_attribute_names(&self, names: Vec<String>) -> LDConfig {
+		LDConfig { private_attribute_names: names, ..self.clone() }
+	}
+
+	pub fn with_feature_store_factory(&self, factory: &'static dyn Fn() -> FeatureStore) -> LDConfig {
+		LDConfig { feature_store_factory: factory, ..self.clone() }
+	}
+}
+
+impl Default for HttpConfig {
+	fn default() -> HttpConfig {
+		HttpConfig {
+			proxy: None,
+			root_certificates: vec![],
+			connect_timeout_millis: 2000,
+			request_timeout_millis: 10000,
+			headers: vec![]
 		}
 	}
+}
 
-	pub fn with_feature_store_factory(&self, factory: &'static Fn() -> FeatureStore) -> LDConfig {
-		LDConfig {
-			base_uri: self.base_uri.clone(),
-			polling_interval_millis: self.polling_interval_millis,
-			feature_store_factory: factory
+impl HttpConfig {
+	pub fn with_proxy(&self, url: String) -> HttpConfig {
+		HttpConfig {
+			proxy: Some(ProxyConfig { url, username: None, password: None }),
+			..self.clone()
 		}
 	}
+
+	// Sets credentials for the proxy; with_proxy must have been called first.
+	pub fn with_proxy_auth(&self, username: String, password: String) -> Result<HttpConfig, String> {
+		match self.proxy {
+			Some(ref p) => Ok(HttpConfig {
+				proxy: Some(ProxyConfig { url: p.url.clone(), username: Some(username), password: Some(password) }),
+				..self.clone()
+			}),
+			None => Err("proxy credentials were given without a proxy URL".to_owned())
+		}
+	}
+
+	pub fn with_root_certificate(&self, pem: Vec<u8>) -> HttpConfig {
+		let mut certs = self.root_certificates.clone();
+		certs.push(pem);
+		HttpConfig { root_certificates: certs, ..self.clone() }
+	}
+
+	pub fn with_connect_timeout_millis(&self, millis: u64) -> HttpConfig {
+		HttpConfig { connect_timeout_millis: millis, ..self.clone() }
+	}
+
+	pub fn with_request_timeout_millis(&self, millis: u64) -> HttpConfig {
+		HttpConfig { request_timeout_millis: millis, ..self.clone() }
+	}
+
+	pub fn with_header(&self, name: String, value: String) -> HttpConfig {
+		let mut headers = self.headers.clone();
+		headers.push((name, value));
+		HttpConfig { headers, ..self.clone() }
+	}
+}
+
+fn env_var(name: &str) -> Result<Option<String>, String> {
+	match env::var(name) {
+		Ok(s) => Ok(Some(s)),
+		Err(env::VarError::NotPresent) => Ok(None),
+		Err(env::VarError::NotUnicode(_)) => Err(format!("{} is not valid Unicode", name))
+	}
+}
+
+fn parse_bool(name: &str, s: &str) -> Result<bool, String> {
+	match s.trim().to_lowercase().as_str() {
+		"true" | "1" | "yes" => Ok(true),
+		"false" | "0" | "no" | "" => Ok(false),
+		_ => Err(format!("{} must be true or false, got \"{}\"", name, s))
+	}
+}
+
+fn parse_millis(name: &str, s: &str) -> Result<u64, String> {
+	s.trim().parse::<u64>()
+		.map_err(|_| format!("{} must be a whole number of milliseconds, got \"{}\"", name, s))
+}
+
+fn parse_count(name: &str, s: &str) -> Result<usize, String> {
+	s.trim().parse::<usize>()
+		.map_err(|_| format!("{} must be a whole number, got \"{}\"", name, s))
 }
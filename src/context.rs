@@ -0,0 +1,253 @@
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use attribute_ref::AttributeRef;
+use user::LDUser;
+
+
+// An evaluation context: the thing that flags are evaluated against. A context has a kind, such
+// as "user", "organization" or "device", a key that is unique within that kind, and any number
+// of other attributes. A multi-kind context combines several contexts of different kinds, so
+// that one evaluation can target e.g. both the user and the organization they belong to.
+#[derive(Clone)]
+#[derive(Debug, PartialEq)]
+pub struct LDContext {
+	kind: String,
+	key: String,
+	attributes: HashMap<String, Value>,
+	private_attributes: Vec<String>,
+	// Only used for multi-kind contexts, which have no key or attributes of their own.
+	contexts: Vec<LDContext>
+}
+
+const MULTI_KIND: &str = "multi";
+
+impl LDContext {
+
+	pub fn builder(kind: &str, key: String) -> LDContextBuilder {
+		LDContextBuilder {
+			kind: kind.to_owned(),
+			key,
+			attributes: HashMap::new(),
+			private_attributes: vec![]
+		}
+	}
+
+	// Combines single-kind contexts into a multi-kind context. Each kind may only appear once.
+	// Combining just one context returns that context unchanged.
+	pub fn multi(contexts: Vec<LDContext>) -> Result<LDContext, String> {
+		if contexts.is_empty() {
+			return Err("a multi-kind context must contain at least one context".to_owned());
+		}
+		let mut kinds: Vec<&str> = vec![];
+		for c in &contexts {
+			if c.is_multi() {
+				return Err("a multi-kind context cannot contain another multi-kind context".to_owned());
+			}
+			if kinds.contains(&c.kind.as_str()) {
+				return Err(format!("context kind \"{}\" appears more than once", c.kind));
+			}
+			kinds.push(&c.kind);
+		}
+		if contexts.len() == 1 {
+			return Ok(contexts.into_iter().next().unwrap());
+		}
+		let mut sorted = contexts;
+		sorted.sort_by(|a, b| a.kind.cmp(&b.kind));
+		Ok(LDContext {
+			kind: MULTI_KIND.to_owned(),
+			key: String::new(),
+			attributes: HashMap::new(),
+			private_attributes: vec![],
+			contexts: sorted
+		})
+	}
+
+	pub fn kind(&self) -> &str {
+		&self.kind
+	}
+
+	pub fn key(&self) -> &str {
+		&self.key
+	}
+
+	// A string that uniquely identifies this context: just the key for a user, otherwise the
+	// kind and key of each individual context, with ':' and '%' escaped so that it's unambiguous.
+	pub fn canonical_key(&self) -> String {
+		if self.kind == "user" {
+			return self.key.clone();
+		}
+		let parts: Vec<String> = self.individual_contexts().iter()
+			.map(|c| format!("{}:{}", c.kind, c.key.replace("%", "%25").replace(":", "%3A")))
+			.collect();
+		parts.join(":")
+	}
+
+	pub fn is_multi(&self) -> bool {
+		self.kind == MULTI_KIND
+	}
+
+	pub fn private_attributes(&self) -> &Vec<String> {
+		&self.private_attributes
+	}
+
+	// The kinds of all of the individual contexts within this one.
+	pub fn kinds(&self) -> Vec<&str> {
+		if self.is_multi() {
+			self.contexts.iter().map(|c| c.kind.as_str()).collect()
+		} else {
+			vec![self.kind.as_str()]
+		}
+	}
+
+	// The single-kind contexts that make up this one; just this context if it isn't multi-kind.
+	pub fn individual_contexts(&self) -> Vec<&LDContext> {
+		if self.is_multi() {
+			self.contexts.iter().collect()
+		} else {
+			vec![self]
+		}
+	}
+
+	// The names of the attributes that are set, other than "kind" and "key".
+	pub fn attribute_names(&self) -> Vec<&String> {
+		let mut names: Vec<&String> = self.attributes.keys().collect();
+		names.sort();
+		names
+	}
+
+	// Finds the individual context of the given kind, if there is one.
+	pub fn as_kind(&self, kind: &str) -> Option<&LDContext> {
+		if self.is_multi() {
+			self.contexts.iter().find(|c| c.kind == kind)
+		} else if self.kind == kind {
+			Some(self)
+		} else {
+			None
+		}
+	}
+
+	// Looks up an attribute of a single-kind context; returns null if it isn't set.
+	pub fn get_value(&self, attr: &str) -> Value {
+		match attr {
+			"kind" => json!(self.kind),
+			"key" => json!(self.key),
+			_ => self.attributes.get(attr).cloned().unwrap_or(Value::Null)
+		}
+	}
+
+	// Looks up a possibly nested attribute; returns null if any part of the path doesn't exist.
+	pub fn get_value_for_ref(&self, attr_ref: &AttributeRef) -> Value {
+		if !attr_ref.is_valid() {
+			return Value::Null;
+		}
+		let mut components = attr_ref.components().iter();
+		let mut value = match components.next() {
+			Some(name) => self.get_value(name),
+			None => return Value::Null
+		};
+		for name in components {
+			value = match value {
+				Value::Object(mut map) => map.remove(name).unwrap_or(Value::Null),
+				_ => return Value::Null
+			};
+		}
+		value
+	}
+}
+
+// A user is a context of kind "user". Built-in and custom user attributes both become top-level
+// context attributes. A custom attribute with the same name as a built-in one, such as "email",
+// "key" or "kind", is dropped, even if the built-in attribute isn't set: evaluation never sees it
+// (see LDUser::get_value_for_evaluation), so it mustn't turn into the built-in attribute here.
+const USER_BUILT_INS: [&str; 9] =
+	["secondary", "ip", "email", "name", "avatar", "firstName", "lastName", "anonymous", "country"];
+
+impl<'a> From<&'a LDUser> for LDContext {
+	fn from(user: &'a LDUser) -> LDContext {
+		let mut attributes: HashMap<String, Value> = HashMap::new();
+		if let Some(ref custom) = user.custom {
+			for (name, value) in custom {
+				if name != "key" && name != "kind" && !USER_BUILT_INS.contains(&name.as_str()) {
+					attributes.insert(name.clone(), value.clone());
+				}
+			}
+		}
+		for attr in USER_BUILT_INS.iter() {
+			let value = user.get_value_for_evaluation(&attr.to_string());
+			if !value.is_null() {
+				attributes.insert(attr.to_string(), value);
+			}
+		}
+		LDContext {
+			kind: "user".to_owned(),
+			key: user.key.clone(),
+			attributes,
+			private_attributes: user.privateAttributeNames.clone().unwrap_or_default(),
+			contexts: vec![]
+		}
+	}
+}
+
+pub struct LDContextBuilder {
+	kind: String,
+	key: String,
+	attributes: HashMap<String, Value>,
+	private_attributes: Vec<String>
+}
+
+impl LDContextBuilder {
+
+	pub fn name(self, value: String) -> LDContextBuilder {
+		self.set("name", Value::String(value))
+	}
+
+	pub fn anonymous(self, value: bool) -> LDContextBuilder {
+		self.set("anonymous", Value::Bool(value))
+	}
+
+	// Sets any attribute other than "kind" and "key", which are fixed when the builder is created.
+	pub fn set(mut self, name: &str, value: Value) -> LDContextBuilder {
+		if name != "kind" && name != "key" {
+			self.attributes.insert(name.to_owned(), value);
+		}
+		self
+	}
+
+	pub fn private_attribute(mut self, name: &str) -> LDContextBuilder {
+		if !self.private_attributes.iter().any(|n| n == name) {
+			self.private_attributes.push(name.to_owned());
+		}
+		self
+	}
+
+	pub fn build(self) -> Result<LDContext, String> {
+		validate_kind(&self.kind)?;
+		if self.key.is_empty() {
+			return Err(format!("context of kind \"{}\" must have a non-empty key", self.kind));
+		}
+		Ok(LDContext {
+			kind: self.kind,
+			key: self.key,
+			attributes: self.attributes,
+			private_attributes: self.private_attributes,
+			contexts: vec![]
+		})
+	}
+}
+
+// Kinds may only contain ASCII letters, digits, '.', '-' and '_'; "kind" and "multi" are reserved.
+fn validate_kind(kind: &str) -> Result<(), String> {
+	if kind.is_empty() {
+		return Err("context kind must not be empty".to_owned());
+	}
+	if kind == "kind" || kind == MULTI_KIND {
+		return Err(format!("\"{}\" is not a valid context kind", kind));
+	}
+	if !kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_') {
+		return Err(format!("context kind \"{}\" may only contain letters, digits, '.', '-' and '_'", kind));
+	}
+	Ok(())
+}
@@ -0,0 +1,153 @@
+
+use std::env::consts;
+use std::sync::Mutex;
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use config::LDConfig;
+use event::current_time_millis;
+
+
+// Identifies one SDK instance in diagnostic events. Only the last six characters of the SDK
+// key are included, which is enough to tell environments apart without revealing the key.
+pub struct DiagnosticId {
+	diagnostic_id: String,
+	sdk_key_suffix: String
+}
+
+impl DiagnosticId {
+	pub fn new(sdk_key: &str) -> DiagnosticId {
+		let suffix_start = sdk_key.char_indices().rev().nth(5).map_or(0, |(i, _)| i);
+		DiagnosticId {
+			diagnostic_id: Uuid::new_v4().to_string(),
+			sdk_key_suffix: sdk_key[suffix_start..].to_owned()
+		}
+	}
+
+	fn to_json(&self) -> Value {
+		json!({ "diagnosticId": self.diagnostic_id, "sdkKeySuffix": self.sdk_key_suffix })
+	}
+}
+
+// The most initialization attempts kept between periodic diagnostic events. Nothing takes them
+// when diagnostic events are disabled, so without a limit they would pile up for as long as
+// the data source keeps failing.
+const MAX_STREAM_INITS: usize = 100;
+
+// Collects the results of attempts to initialize the data source, which happen on the polling
+// thread, until they are reported in the next periodic diagnostic event. Only the most recent
+// MAX_STREAM_INITS are kept.
+pub struct DiagnosticsRecorder {
+	stream_inits: Mutex<Vec<Value>>
+}
+
+impl DiagnosticsRecorder {
+	pub fn new() -> DiagnosticsRecorder {
+		DiagnosticsRecorder { stream_inits: Mutex::new(vec![]) }
+	}
+
+	pub fn record_stream_init(&self, timestamp: u64, duration_millis: u64, failed: bool) {
+		let mut stream_inits = self.stream_inits.lock().unwrap();
+		if stream_inits.len() >= MAX_STREAM_INITS {
+			stream_inits.remove(0);
+		}
+		stream_inits.push(json!({
+			"timestamp": timestamp,
+			"durationMillis": duration_millis,
+			"failed": failed
+		}));
+	}
+
+	fn take_stream_inits(&self) -> Vec<Value> {
+		self.stream_inits.lock().unwrap().drain(..).collect()
+	}
+}
+
+impl Default for DiagnosticsRecorder {
+	fn default() -> DiagnosticsRecorder {
+		DiagnosticsRecorder::new()
+	}
+}
+
+// Counts kept by the event processor between periodic diagnostic events.
+pub struct DiagnosticStats {
+	pub data_since: u64,
+	pub dropped_events: u64,
+	pub deduplicated_users: u64,
+	pub events_in_last_batch: u64
+}
+
+impl DiagnosticStats {
+	pub fn new() -> DiagnosticStats {
+		DiagnosticStats {
+			data_since: current_time_millis(),
+			dropped_events: 0,
+			deduplicated_users: 0,
+			events_in_last_batch: 0
+		}
+	}
+}
+
+impl Default for DiagnosticStats {
+	fn default() -> DiagnosticStats {
+		DiagnosticStats::new()
+	}
+}
+
+// Sent once when the SDK starts. This describes the configuration, but never includes the SDK
+// key, proxy credentials or anything else that might be secret.
+pub fn make_init_event(id: &DiagnosticId, config: &LDConfig) -> Value {
+	let defaults = LDConfig::default();
+	let proxy = config.http.proxy.as_ref();
+	json!({
+		"kind": "diagnostic-init",
+		"id": id.to_json(),
+		"creationDate": current_time_millis(),
+		"sdk": {
+			"name": "rust-client",
+			"version": env!("CARGO_PKG_VERSION"),
+			"wrapperName": config.wrapper_name,
+			"wrapperVersion": config.wrapper_version
+		},
+		"configuration": {
+			"customBaseURI": config.base_uri != defaults.base_uri,
+			"customStreamURI": config.stream_uri != defaults.stream_uri,
+			"customEventsURI": config.events_uri != defaults.events_uri,
+			"offline": config.offline,
+			"streamingDisabled": true,
+			"pollingIntervalMillis": config.polling_interval_millis,
+			"startWaitMillis": config.start_wait_millis,
+			"eventsCapacity": config.events_capacity,
+			"eventsFlushIntervalMillis": config.flush_interval_millis,
+			"userKeysCapacity": config.user_keys_capacity,
+			"userKeysFlushIntervalMillis": config.user_keys_flush_interval_millis,
+			"allAttributesPrivate": config.all_attributes_private,
+			"connectTimeoutMillis": config.http.connect_timeout_millis,
+			// the closest thing in the diagnostic schema to a timeout for the whole request
+			"socketTimeoutMillis": config.http.request_timeout_millis,
+			"usingProxy": proxy.is_some(),
+			"usingProxyAuthenticator": proxy.is_some_and(|p| p.username.is_some()),
+			"diagnosticRecordingIntervalMillis": config.diagnostic_recording_interval_millis
+		},
+		"platform": {
+			"name": "Rust",
+			"osName": consts::OS,
+			"osArch": consts::ARCH
+		}
+	})
+}
+
+// Sent every diagnostic_recording_interval_millis, with statistics since the last one.
+pub fn make_periodic_event(id: &DiagnosticId, stats: &DiagnosticStats, recorder: &DiagnosticsRecorder) -> Value {
+	json!({
+		"kind": "diagnostic",
+		"id": id.to_json(),
+		"creationDate": current_time_millis(),
+		"dataSinceDate": stats.data_since,
+		"droppedEvents": stats.dropped_events,
+		"deduplicatedUsers": stats.deduplicated_users,
+		"eventsInLastBatch": stats.events_in_last_batch,
+		"streamInits": recorder.take_stream_inits()
+	})
+}
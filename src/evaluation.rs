@@ -0,0 +1,86 @@
+
+use serde_json::Value;
+
+
+// The result of evaluating a flag: the value, and why that value was chosen.
+#[derive(Clone)]
+#[derive(Debug, PartialEq)]
+pub struct EvaluationDetail {
+	pub value: Value,
+	// The index of the value in the flag's variations; None if the default value was used.
+	pub variation_index: Option<u32>,
+	pub reason: EvaluationReason
+}
+
+#[derive(Clone)]
+#[derive(Debug, PartialEq)]
+pub enum EvaluationReason {
+	// The flag is off, so the off variation was used.
+	Off,
+	// The context's key was one of the flag's individual targets.
+	TargetMatch,
+	// The context matched one of the flag's rules. in_experiment is true if the rule's rollout
+	// is an experiment and the context was allocated to a tracked variation.
+	RuleMatch { rule_index: usize, rule_id: Option<String>, in_experiment: bool },
+	// A prerequisite flag didn't have the required variation, so the off variation was used.
+	PrerequisiteFailed { prerequisite_key: String },
+	// No targets or rules matched, so the fallthrough variation or rollout was used.
+	Fallthrough { in_experiment: bool },
+	Error { error_kind: ErrorKind }
+}
+
+#[derive(Clone, Copy)]
+#[derive(Debug, PartialEq)]
+pub enum ErrorKind {
+	// The flag data was invalid, e.g. a variation index was out of range.
+	MalformedFlag,
+	FlagNotFound
+}
+
+impl EvaluationReason {
+
+	pub fn in_experiment(&self) -> bool {
+		match *self {
+			EvaluationReason::RuleMatch { in_experiment, .. } => in_experiment,
+			EvaluationReason::Fallthrough { in_experiment } => in_experiment,
+			_ => false
+		}
+	}
+
+	// The representation used in events and in the client-side bootstrap data.
+	pub fn to_json(&self) -> Value {
+		match *self {
+			EvaluationReason::Off => json!({ "kind": "OFF" }),
+			EvaluationReason::TargetMatch => json!({ "kind": "TARGET_MATCH" }),
+			EvaluationReason::RuleMatch { rule_index, ref rule_id, in_experiment } => {
+				let mut out = json!({ "kind": "RULE_MATCH", "ruleIndex": rule_index });
+				if let Some(ref id) = *rule_id {
+					out["ruleId"] = json!(id);
+				}
+				if in_experiment {
+					out["inExperiment"] = json!(true);
+				}
+				out
+			},
+			EvaluationReason::PrerequisiteFailed { ref prerequisite_key } =>
+				json!({ "kind": "PREREQUISITE_FAILED", "prerequisiteKey": prerequisite_key }),
+			EvaluationReason::Fallthrough { in_experiment } => {
+				let mut out = json!({ "kind": "FALLTHROUGH" });
+				if in_experiment {
+					out["inExperiment"] = json!(true);
+				}
+				out
+			},
+			EvaluationReason::Error { error_kind } => json!({ "kind": "ERROR", "errorKind": error_kind.as_str() })
+		}
+	}
+}
+
+impl ErrorKind {
+	pub fn as_str(&self) -> &'static str {
+		match *self {
+			ErrorKind::MalformedFlag => "MALFORMED_FLAG",
+			ErrorKind::FlagNotFound => "FLAG_NOT_FOUND"
+		}
+	}
+}
@@ -3,35 +3,110 @@ extern crate time;
 
 use serde_json::Value;
 
-use user::LDUser;
+use context::LDContext;
+use evaluation::{EvaluationDetail, EvaluationReason};
+use flag::FeatureFlag;
 
 
+// Everything that can be sent to LaunchDarkly's analytics pipeline.
+#[derive(Clone)]
+#[derive(Debug)]
+pub enum Event {
+	FeatureRequest(FeatureRequestEvent),
+	Identify(IdentifyEvent),
+	Custom(CustomEvent)
+}
+
+// The result of evaluating a flag. These are always counted in summary events; they are only
+// sent individually if the flag has trackEvents set.
+#[derive(Clone)]
+#[derive(Debug)]
 pub struct FeatureRequestEvent {
 	pub creationDate: u64,
 	pub key: String,
-	pub kind: String,
-	pub user: LDUser,
+	pub context: LDContext,
 	pub value: Value,
+	// The index of the value in the flag's variations; None if the default value was used.
+	pub variation: Option<u32>,
 	pub default: Option<Value>,
+	// None if the flag doesn't exist.
 	pub version: Option<u32>,
-	pub prereqOf: String
-}
-
-// pub fn feature_request(key: String, user: &LDUser, value: &Value, default: Option<Value>,
-// 					   version: Option<u32>, prereq_of: &String) -> FeatureRequestEvent {
-// 	FeatureRequestEvent {
-// 		creationDate: current_time_millis(),
-// 		key: key,
-// 		kind: String::from("feature"),
-// 		user: user.clone(),
-// 		value: value.clone(),
-// 		default: default,
-// 		version: version,
-// 		prereqOf: prereq_of.clone()
-// 	}
-// }
-
-// fn current_time_millis() -> u64 {
-// 	let ts = time::get_time();
-// 	return ts.sec as u64 + ts.nsec as u64 / 1000 / 1000;
-// }
+	pub prereqOf: Option<String>,
+	// Whether to send a full feature event, rather than just counting it in the summary.
+	pub trackEvents: bool,
+	// Only included in the event for experiments.
+	pub reason: Option<EvaluationReason>
+}
+
+// Tells LaunchDarkly about a user (or other context) without evaluating a flag.
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct IdentifyEvent {
+	pub creationDate: u64,
+	pub context: LDContext
+}
+
+// A metric recorded by the application, e.g. a conversion for an experiment.
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct CustomEvent {
+	pub creationDate: u64,
+	pub key: String,
+	pub context: LDContext,
+	pub data: Option<Value>,
+	pub metricValue: Option<f64>
+}
+
+pub fn feature_request(flag: &FeatureFlag, context: &LDContext, detail: &EvaluationDetail, default: Option<Value>,
+					   prereq_of: Option<String>) -> FeatureRequestEvent {
+	let is_experiment = flag.is_experiment(&detail.reason);
+	FeatureRequestEvent {
+		creationDate: current_time_millis(),
+		key: flag.key.clone(),
+		context: context.clone(),
+		value: detail.value.clone(),
+		variation: detail.variation_index,
+		default,
+		version: Some(flag.version),
+		prereqOf: prereq_of,
+		trackEvents: flag.trackEvents || is_experiment,
+		reason: if is_experiment { Some(detail.reason.clone()) } else { None }
+	}
+}
+
+pub fn unknown_feature_request(key: &str, context: &LDContext, default: Value) -> FeatureRequestEvent {
+	FeatureRequestEvent {
+		creationDate: current_time_millis(),
+		key: key.to_owned(),
+		context: context.clone(),
+		value: default.clone(),
+		variation: None,
+		default: Some(default),
+		version: None,
+		prereqOf: None,
+		trackEvents: false,
+		reason: None
+	}
+}
+
+pub fn identify(context: &LDContext) -> Event {
+	Event::Identify(IdentifyEvent {
+		creationDate: current_time_millis(),
+		context: context.clone()
+	})
+}
+
+pub fn custom(key: &str, context: &LDContext, data: Option<Value>, metric_value: Option<f64>) -> Event {
+	Event::Custom(CustomEvent {
+		creationDate: current_time_millis(),
+		key: key.to_owned(),
+		context: context.clone(),
+		data,
+		metricValue: metric_value
+	})
+}
+
+pub fn current_time_millis() -> u64 {
+	let ts = time::get_time();
+	ts.sec as u64 * 1000 + ts.nsec as u64 / 1000 / 1000
+}
@@ -0,0 +1,490 @@
+
+use std::cmp;
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::sync::mpsc::{Sender, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use reqwest;
+use reqwest::StatusCode;
+use serde_json;
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use config::LDConfig;
+use diagnostics;
+use diagnostics::{DiagnosticId, DiagnosticStats, DiagnosticsRecorder};
+use context::LDContext;
+use event::{Event, FeatureRequestEvent};
+use lru_cache::LruCache;
+use user_filter::UserFilter;
+
+
+// Collects analytics events and delivers them to LaunchDarkly in batches from a background
+// thread, so that recording an event never blocks on the network.
+pub struct EventProcessor {
+	// None if events are disabled, in which case all events are discarded.
+	sender: Option<Mutex<Sender<EventMessage>>>
+}
+
+enum EventMessage {
+	Event(Box<Event>),
+	Flush,
+	// delivers everything buffered, then stops the background thread and replies
+	Close(Sender<()>)
+}
+
+// The parts of the event processor that are only needed if diagnostic events are enabled.
+struct DiagnosticsTask {
+	id: DiagnosticId,
+	stats: DiagnosticStats,
+	recorder: Arc<DiagnosticsRecorder>,
+	interval: Duration,
+	// sent as soon as the background thread starts
+	init_event: Option<Value>
+}
+
+struct EventProcessorTask {
+	sender: EventSender,
+	filter: UserFilter,
+	capacity: usize,
+	flush_interval: Duration,
+	// contexts that have been sent in an index event recently, so they needn't be sent again
+	user_keys: LruCache,
+	user_keys_flush_interval: Duration,
+	buffer: Vec<Value>,
+	summary: EventSummary,
+	diagnostics: Option<DiagnosticsTask>,
+	// set if LaunchDarkly rejects the SDK key, after which there's no point sending anything
+	disabled: bool
+}
+
+// Counts of flag evaluations since the last flush, so that evaluations can be reported without
+// sending an event for every one of them.
+struct EventSummary {
+	start_date: u64,
+	end_date: u64,
+	features: HashMap<String, FlagSummary>
+}
+
+struct FlagSummary {
+	default: Option<Value>,
+	counters: HashMap<CounterKey, Counter>
+}
+
+#[derive(Hash, Eq, PartialEq)]
+struct CounterKey {
+	version: Option<u32>,
+	variation: Option<u32>,
+	// the JSON representation of the value, since Value can't be hashed
+	value: String
+}
+
+struct Counter {
+	value: Value,
+	count: u64
+}
+
+pub struct EventSender {
+	sdk_key: String,
+	events_uri: String,
+	client: reqwest::Client
+}
+
+impl EventProcessor {
+	pub fn new(sdk_key: String, config: &LDConfig, http_client: reqwest::Client,
+			   diagnostics_recorder: &Arc<DiagnosticsRecorder>) -> EventProcessor {
+		if config.offline || !config.send_events {
+			return EventProcessor { sender: None };
+		}
+		let diagnostics = if config.diagnostic_opt_out {
+			None
+		} else {
+			let id = DiagnosticId::new(&sdk_key);
+			let init_event = diagnostics::make_init_event(&id, config);
+			Some(DiagnosticsTask {
+				id,
+				stats: DiagnosticStats::new(),
+				recorder: diagnostics_recorder.clone(),
+				interval: Duration::from_millis(cmp::max(config.diagnostic_recording_interval_millis, 60000)),
+				init_event: Some(init_event)
+			})
+		};
+		let (tx, rx): (Sender<EventMessage>, Receiver<EventMessage>) = mpsc::channel();
+		let mut task = EventProcessorTask {
+			sender: EventSender::new(sdk_key, &config.events_uri, http_client),
+			filter: UserFilter::new(config),
+			capacity: config.events_capacity,
+			flush_interval: Duration::from_millis(config.flush_interval_millis),
+			user_keys: LruCache::new(config.user_keys_capacity),
+			user_keys_flush_interval: Duration::from_millis(config.user_keys_flush_interval_millis),
+			buffer: vec![],
+			summary: EventSummary::new(),
+			diagnostics,
+			disabled: false
+		};
+		thread::spawn(move || task.run(rx));
+		EventProcessor { sender: Some(Mutex::new(tx)) }
+	}
+
+	pub fn send(&self, event: Event) {
+		self.post(EventMessage::Event(Box::new(event)));
+	}
+
+	// Asks the background thread to deliver everything buffered so far, without waiting for it.
+	pub fn flush(&self) {
+		self.post(EventMessage::Flush);
+	}
+
+	// Delivers any pending events and stops the background thread, waiting until it's done.
+	pub fn close(&self) {
+		let (tx, rx) = mpsc::channel();
+		self.post(EventMessage::Close(tx));
+		// if events are disabled there's nothing to wait for, and the reply is never sent
+		let _ = rx.recv();
+	}
+
+	fn post(&self, message: EventMessage) {
+		if let Some(ref sender) = self.sender {
+			// this can only fail if the background thread has gone away, in which case the
+			// event could never be delivered anyway
+			let _ = sender.lock().unwrap().send(message);
+		}
+	}
+}
+
+impl EventProcessorTask {
+	fn run(&mut self, rx: Receiver<EventMessage>) {
+		let mut next_flush = Instant::now() + self.flush_interval;
+		let mut next_user_keys_flush = Instant::now() + self.user_keys_flush_interval;
+		// if diagnostics are disabled, this is never reached
+		let mut next_diagnostic = Instant::now() +
+			self.diagnostics.as_ref().map_or(Duration::from_secs(u32::MAX as u64), |d| d.interval);
+		self.send_diagnostic_init_event();
+		loop {
+			let now = Instant::now();
+			let deadline = cmp::min(next_flush, cmp::min(next_user_keys_flush, next_diagnostic));
+			let timeout = if deadline > now { deadline - now } else { Duration::from_millis(0) };
+			match rx.recv_timeout(timeout) {
+				Ok(EventMessage::Event(event)) => self.process(*event),
+				Ok(EventMessage::Flush) => self.flush(),
+				Ok(EventMessage::Close(reply)) => {
+					self.flush();
+					let _ = reply.send(());
+					return;
+				},
+				Err(RecvTimeoutError::Timeout) => {
+					let now = Instant::now();
+					if now >= next_flush {
+						self.flush();
+						next_flush = now + self.flush_interval;
+					}
+					if now >= next_user_keys_flush {
+						self.user_keys.clear();
+						next_user_keys_flush = now + self.user_keys_flush_interval;
+					}
+					if now >= next_diagnostic {
+						self.send_periodic_diagnostic_event();
+						if let Some(ref d) = self.diagnostics {
+							next_diagnostic = now + d.interval;
+						}
+					}
+				},
+				Err(RecvTimeoutError::Disconnected) => {
+					self.flush();
+					return;
+				}
+			}
+		}
+	}
+
+	fn process(&mut self, event: Event) {
+		if self.disabled {
+			return;
+		}
+		match event {
+			Event::FeatureRequest(e) => {
+				self.summary.add(&e);
+				self.notice_context(&e.context, e.creationDate);
+				if e.trackEvents {
+					let mut out = Map::new();
+					out.insert("kind".to_owned(), json!("feature"));
+					out.insert("creationDate".to_owned(), json!(e.creationDate));
+					out.insert("key".to_owned(), json!(e.key));
+					add_context_keys(&mut out, &e.context);
+					out.insert("value".to_owned(), e.value);
+					if let Some(variation) = e.variation {
+						out.insert("variation".to_owned(), json!(variation));
+					}
+					if let Some(default) = e.default {
+						out.insert("default".to_owned(), default);
+					}
+					if let Some(version) = e.version {
+						out.insert("version".to_owned(), json!(version));
+					}
+					if let Some(prereq_of) = e.prereqOf {
+						out.insert("prereqOf".to_owned(), json!(prereq_of));
+					}
+					if let Some(reason) = e.reason {
+						out.insert("reason".to_owned(), reason.to_json());
+					}
+					self.add(Value::Object(out));
+				}
+			},
+			Event::Identify(e) => {
+				// the identify event contains the whole context, so no index event is needed
+				self.user_keys.notice(&e.context.canonical_key());
+				let mut out = Map::new();
+				out.insert("kind".to_owned(), json!("identify"));
+				out.insert("creationDate".to_owned(), json!(e.creationDate));
+				out.insert("key".to_owned(), json!(e.context.key()));
+				add_context(&mut out, &self.filter, &e.context);
+				self.add(Value::Object(out));
+			},
+			Event::Custom(e) => {
+				self.notice_context(&e.context, e.creationDate);
+				let mut out = Map::new();
+				out.insert("kind".to_owned(), json!("custom"));
+				out.insert("creationDate".to_owned(), json!(e.creationDate));
+				out.insert("key".to_owned(), json!(e.key));
+				add_context_keys(&mut out, &e.context);
+				if let Some(data) = e.data {
+					out.insert("data".to_owned(), data);
+				}
+				if let Some(metric_value) = e.metricValue {
+					out.insert("metricValue".to_owned(), json!(metric_value));
+				}
+				self.add(Value::Object(out));
+			}
+		}
+	}
+
+	// Sends the full context in an index event, unless it has been sent recently. Other events
+	// then only need to refer to it by key.
+	fn notice_context(&mut self, context: &LDContext, creation_date: u64) {
+		if self.user_keys.notice(&context.canonical_key()) {
+			if let Some(ref mut d) = self.diagnostics {
+				d.stats.deduplicated_users += 1;
+			}
+			return;
+		}
+		let mut out = Map::new();
+		out.insert("kind".to_owned(), json!("index"));
+		out.insert("creationDate".to_owned(), json!(creation_date));
+		add_context(&mut out, &self.filter, context);
+		self.add(Value::Object(out));
+	}
+
+	fn add(&mut self, event: Value) {
+		if self.buffer.len() < self.capacity {
+			self.buffer.push(event);
+		} else if let Some(ref mut d) = self.diagnostics {
+			// TODO: log that the buffer is full and events are being dropped
+			d.stats.dropped_events += 1;
+		}
+	}
+
+	fn flush(&mut self) {
+		if self.disabled || (self.buffer.is_empty() && self.summary.features.is_empty()) {
+			return;
+		}
+		let mut output: Vec<Value> = self.buffer.drain(..).collect();
+		if !self.summary.features.is_empty() {
+			output.push(self.summary.make_output_event());
+			self.summary = EventSummary::new();
+		}
+		if let Some(ref mut d) = self.diagnostics {
+			d.stats.events_in_last_batch = output.len() as u64;
+		}
+		match self.sender.send_events(&Value::Array(output)) {
+			Ok(_) => (),
+			Err(EventSendError::Unauthorized) => {
+				self.disabled = true;
+				self.buffer.clear();
+			},
+			Err(EventSendError::Failed(_)) => () // TODO: error logging
+		}
+	}
+
+	fn send_diagnostic_init_event(&mut self) {
+		let init_event = self.diagnostics.as_mut().and_then(|d| d.init_event.take());
+		if let Some(event) = init_event {
+			self.send_diagnostic_event(&event);
+		}
+	}
+
+	fn send_periodic_diagnostic_event(&mut self) {
+		let event = match self.diagnostics {
+			Some(ref mut d) => {
+				let event = diagnostics::make_periodic_event(&d.id, &d.stats, &d.recorder);
+				d.stats = DiagnosticStats::new();
+				event
+			},
+			None => return
+		};
+		self.send_diagnostic_event(&event);
+	}
+
+	fn send_diagnostic_event(&mut self, event: &Value) {
+		if self.disabled {
+			return;
+		}
+		match self.sender.send_diagnostic_event(event) {
+			Ok(_) => (),
+			Err(EventSendError::Unauthorized) => {
+				self.disabled = true;
+				self.buffer.clear();
+			},
+			Err(EventSendError::Failed(_)) => () // TODO: error logging
+		}
+	}
+}
+
+impl EventSummary {
+	fn new() -> EventSummary {
+		EventSummary { start_date: 0, end_date: 0, features: HashMap::new() }
+	}
+
+	fn add(&mut self, e: &FeatureRequestEvent) {
+		if self.start_date == 0 || e.creationDate < self.start_date {
+			self.start_date = e.creationDate;
+		}
+		if e.creationDate > self.end_date {
+			self.end_date = e.creationDate;
+		}
+		let flag_summary = self.features.entry(e.key.clone()).or_insert_with(|| FlagSummary {
+			default: None,
+			counters: HashMap::new()
+		});
+		// prerequisite evaluations have no default, so the flag's default comes from whichever
+		// direct evaluation is seen first
+		if flag_summary.default.is_none() {
+			flag_summary.default = e.default.clone();
+		}
+		let key = CounterKey { version: e.version, variation: e.variation, value: e.value.to_string() };
+		flag_summary.counters.entry(key)
+			.or_insert_with(|| Counter { value: e.value.clone(), count: 0 })
+			.count += 1;
+	}
+
+	fn make_output_event(&self) -> Value {
+		let mut features = Map::new();
+		for (key, flag_summary) in &self.features {
+			let counters: Vec<Value> = flag_summary.counters.iter().map(|(counter_key, counter)| {
+				let mut out = json!({ "value": counter.value, "count": counter.count });
+				match counter_key.version {
+					Some(version) => out["version"] = json!(version),
+					None => out["unknown"] = json!(true)
+				}
+				if let Some(variation) = counter_key.variation {
+					out["variation"] = json!(variation);
+				}
+				out
+			}).collect();
+			features.insert(key.clone(), json!({
+				"default": flag_summary.default,
+				"counters": counters
+			}));
+		}
+		json!({
+			"kind": "summary",
+			"startDate": self.start_date,
+			"endDate": self.end_date,
+			"features": features
+		})
+	}
+}
+
+// Users are sent in a "user" property in the traditional format; other kinds of context are
+// sent in a "context" property.
+fn add_context(out: &mut Map<String, Value>, filter: &UserFilter, context: &LDContext) {
+	let name = if context.kind() == "user" { "user" } else { "context" };
+	out.insert(name.to_owned(), filter.filter_context(context));
+}
+
+// Refers to a context that has been sent in an index event: "userKey" for a user, or
+// "contextKeys" with the key of each kind otherwise.
+fn add_context_keys(out: &mut Map<String, Value>, context: &LDContext) {
+	if context.kind() == "user" {
+		out.insert("userKey".to_owned(), json!(context.key()));
+	} else {
+		let mut keys = Map::new();
+		for c in context.individual_contexts() {
+			keys.insert(c.kind().to_owned(), json!(c.key()));
+		}
+		out.insert("contextKeys".to_owned(), Value::Object(keys));
+	}
+}
+
+pub enum EventSendError {
+	// The payload couldn't be delivered, even after retrying; it has been discarded.
+	Failed(String),
+	// The SDK key was rejected. Nothing will ever be delivered with it, so stop trying.
+	Unauthorized
+}
+
+impl EventSender {
+
+	pub fn new(sdk_key: String, events_uri: &str, client: reqwest::Client) -> EventSender {
+		EventSender {
+			sdk_key,
+			events_uri: events_uri.to_owned(),
+			client
+		}
+	}
+
+	pub fn send_events(&self, events: &Value) -> Result<(), EventSendError> {
+		self.post(&format!("{}/bulk", self.events_uri), events)
+	}
+
+	pub fn send_diagnostic_event(&self, event: &Value) -> Result<(), EventSendError> {
+		self.post(&format!("{}/diagnostic", self.events_uri), event)
+	}
+
+	// Posts a gzipped JSON payload. A network error or server error is retried once, after a
+	// short delay; the payload ID lets LaunchDarkly discard the retry if the first attempt did
+	// in fact get through.
+	fn post(&self, uri: &String, payload: &Value) -> Result<(), EventSendError> {
+		let json = serde_json::to_vec(payload)
+			.map_err(|_| EventSendError::Failed("unable to serialize events".to_owned()))?;
+		let body = gzip(&json)
+			.map_err(|_| EventSendError::Failed("unable to compress events".to_owned()))?;
+		let payload_id = Uuid::new_v4().to_string();
+		let mut result = Err(EventSendError::Failed("events were not sent".to_owned()));
+		for attempt in 0..2 {
+			if attempt > 0 {
+				thread::sleep(Duration::from_millis(1000));
+			}
+			let resp_result = self.client.post(uri)
+				.header(reqwest::header::AUTHORIZATION, self.sdk_key.clone())
+				.header(reqwest::header::CONTENT_TYPE, "application/json")
+				.header(reqwest::header::CONTENT_ENCODING, "gzip")
+				.header("X-LaunchDarkly-Payload-ID", payload_id.clone())
+				.body(body.clone())
+				.send();
+			result = match resp_result {
+				Ok(ref resp) if resp.status().is_success() => return Ok(()),
+				Ok(ref resp) if resp.status() == StatusCode::UNAUTHORIZED || resp.status() == StatusCode::FORBIDDEN =>
+					return Err(EventSendError::Unauthorized),
+				Ok(ref resp) if resp.status().is_server_error() =>
+					Err(EventSendError::Failed(format!("unexpected response status {}", resp.status()))),
+				Ok(resp) =>
+					return Err(EventSendError::Failed(format!("unexpected response status {}", resp.status()))),
+				Err(_) => Err(EventSendError::Failed("unable to send events".to_owned()))
+			};
+		}
+		result
+	}
+}
+
+fn gzip(data: &[u8]) -> io::Result<Vec<u8>> {
+	let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+	encoder.write_all(data)?;
+	encoder.finish()
+}
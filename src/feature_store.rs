@@ -1,37 +1,55 @@
 
 use std::collections::HashMap;
+use std::mem;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use flag::FeatureFlag;
 
 // TODO: make this into a trait so there can be both an in-memory implementation and a Redis one.
 
+// The flags are kept in an immutable snapshot that is replaced all at once when new data
+// arrives. Readers only hold the lock long enough to copy a pointer, so evaluations never wait
+// for flags to be parsed or preprocessed, and never copy a flag.
 pub struct FeatureStore {
-	items: HashMap<String, FeatureFlag>
+	items: RwLock<Arc<HashMap<String, Arc<FeatureFlag>>>>,
+	initialized: AtomicBool
 }
 
 impl FeatureStore {
 
 	pub fn in_memory_store() -> FeatureStore {
-		let items: HashMap<String, FeatureFlag> = HashMap::new();
-		FeatureStore { items }
+		FeatureStore { items: RwLock::new(Arc::new(HashMap::new())), initialized: AtomicBool::new(false) }
 	}
 
-	pub fn get(&self, key: &String) -> Option<FeatureFlag> {
-		let opt_flag = self.items.get(key);
-		match opt_flag {
+	pub fn get(&self, key: &String) -> Option<Arc<FeatureFlag>> {
+		let items = self.all();
+		match items.get(key) {
 			Some(flag) => if flag.deleted { None } else { Some(flag.clone()) },
 			None => None
 		}
 	}
 
-	pub fn all(&self) -> HashMap<String, FeatureFlag> {
-		self.items.clone()
+	// All flags, including deleted ones, as they are now; later updates don't change the result.
+	pub fn all(&self) -> Arc<HashMap<String, Arc<FeatureFlag>>> {
+		self.items.read().unwrap().clone()
 	}
 
-	pub fn init(& mut self, flags: &HashMap<String, FeatureFlag>) {
-		self.items.clear();
-		for (key, value) in flags.iter() {
-			self.items.insert(key.clone(), (*value).clone());
+	// True once flags have been received; until then, the store is empty because there's no data
+	// yet, not because there are no flags.
+	pub fn initialized(&self) -> bool {
+		self.initialized.load(Ordering::Acquire)
+	}
+
+	pub fn init(&self, flags: HashMap<String, FeatureFlag>) {
+		let mut items = HashMap::with_capacity(flags.len());
+		for (key, mut flag) in flags {
+			flag.preprocess();
+			items.insert(key, Arc::new(flag));
 		}
+		let old = mem::replace(&mut *self.items.write().unwrap(), Arc::new(items));
+		self.initialized.store(true, Ordering::Release);
+		// readers may still be using the old flags; if not, they're freed here, outside the lock
+		drop(old);
 	}
 }
@@ -1,14 +1,23 @@
 
+use std::collections::HashSet;
+
 use regex::Regex;
 use semver::Version;
 use serde_json::Value;
 use sha1;
-use time::{Timespec, strptime};
+use time::Timespec;
 
+use evaluation::{EvaluationDetail, EvaluationReason, ErrorKind};
+use event;
 use event::FeatureRequestEvent;
 use feature_store::FeatureStore;
-use user::LDUser;
+use attribute_ref::AttributeRef;
+use context::LDContext;
+
 
+// Longer chains of prerequisites are treated as malformed, so that evaluation can't overflow the
+// stack; real flag configurations are nowhere near this deep.
+const MAX_PREREQUISITE_DEPTH: usize = 100;
 
 #[derive(Clone)]
 #[derive(Deserialize)]
@@ -24,47 +33,131 @@ pub struct FeatureFlag {
 	pub fallthrough: VariationOrRollout,
 	pub offVariation: Option<u32>,
 	pub variations: Vec<Value>,
+	#[serde(default)]
+	pub trackEvents: bool,
+	#[serde(default)]
+	pub trackEventsFallthrough: bool,
+	// Full feature events are sent for every evaluation until this time, for debugging.
+	pub debugEventsUntilDate: Option<u64>,
+	// Whether client-side SDKs can use the flag. Newer flag data has clientSideAvailability
+	// instead of clientSide.
+	#[serde(default)]
+	pub clientSide: bool,
+	pub clientSideAvailability: Option<ClientSideAvailability>,
 	pub deleted: bool
 }
 
+#[derive(Clone)]
+#[derive(Deserialize)]
+#[derive(Debug)]
+pub struct ClientSideAvailability {
+	#[serde(default)]
+	pub usingEnvironmentId: bool,
+	#[serde(default)]
+	pub usingMobileKey: bool
+}
+
 impl FeatureFlag {
-	pub fn evaluate(&self, user: &LDUser, store: &FeatureStore) -> (Value, Vec<FeatureRequestEvent>) {
+	pub fn evaluate(&self, context: &LDContext, store: &FeatureStore) -> (EvaluationDetail, Vec<FeatureRequestEvent>) {
 		let mut prereq_events: Vec<FeatureRequestEvent> = vec![];
-		if self.on {
-			let maybe_value = self.evaluate_internal(user, store, &mut prereq_events);
-			if let Some(value) = maybe_value {
-				return (value, prereq_events);
+		let detail = match self.evaluate_internal(context, store, &mut vec![], &mut prereq_events) {
+			Ok(detail) => detail,
+			// a prerequisite cycle makes the whole evaluation invalid, however deep it was found
+			Err(error_kind) => error_detail(error_kind)
+		};
+		(detail, prereq_events)
+	}
+
+	// True if evaluations with this result must be sent as full feature events, because they
+	// are part of an experiment or the matching rule or fallthrough is tracked.
+	pub fn is_experiment(&self, reason: &EvaluationReason) -> bool {
+		if reason.in_experiment() {
+			return true;
+		}
+		match *reason {
+			EvaluationReason::RuleMatch { rule_index, .. } =>
+				self.rules.get(rule_index).is_some_and(|rule| rule.trackEvents),
+			EvaluationReason::Fallthrough { .. } => self.trackEventsFallthrough,
+			_ => false
+		}
+	}
+
+	// True if the flag is available to the JavaScript SDK, which identifies itself by environment ID.
+	pub fn is_client_side(&self) -> bool {
+		match self.clientSideAvailability {
+			Some(ref availability) => availability.usingEnvironmentId,
+			None => self.clientSide
+		}
+	}
+
+	// Converts clause values and targets into the forms that evaluation needs, so that it doesn't
+	// have to parse them every time. The store does this for every flag it receives; flags that
+	// haven't been preprocessed still evaluate the same way, just more slowly.
+	pub fn preprocess(&mut self) {
+		for target in &mut self.targets {
+			target.preprocess();
+		}
+		for rule in &mut self.rules {
+			for clause in &mut rule.clauses {
+				clause.preprocess();
+			}
+			if let Some(ref mut rollout) = rule.rollout {
+				rollout.preprocess();
 			}
 		}
-		let off_variation = self.get_off_variation_value();
-		(off_variation, prereq_events)
+		if let Some(ref mut rollout) = self.fallthrough.rollout {
+			rollout.preprocess();
+		}
 	}
 
-	fn evaluate_internal(&self, user: &LDUser, store: &FeatureStore, prereq_events: &mut Vec<FeatureRequestEvent>) -> Option<Value> {
+	// prereq_chain has the keys of the flags whose prerequisites are being evaluated, outermost first.
+	fn evaluate_internal(&self, context: &LDContext, store: &FeatureStore, prereq_chain: &mut Vec<String>,
+						 prereq_events: &mut Vec<FeatureRequestEvent>) -> Result<EvaluationDetail, ErrorKind> {
+		if !self.on {
+			return Ok(self.off_detail(EvaluationReason::Off));
+		}
+		if !self.prerequisites.is_empty() {
+			if prereq_chain.len() >= MAX_PREREQUISITE_DEPTH {
+				return Err(ErrorKind::MalformedFlag);
+			}
+			prereq_chain.push(self.key.clone());
+		}
 		for prereq in &self.prerequisites {
-			match store.get(&prereq.key) {
-				Some(prereq_flag) => {
-					if prereq_flag.on {
-						let prereq_result = prereq_flag.evaluate_internal(user, store, prereq_events);
-						let variation = prereq_flag.get_variation(Some(prereq.variation));
-						if prereq_result != variation {
-							return None;
-						}
-						// TODO: create event
-						// let event = event::feature_request(prereq_flag.key, user, &prereq_result,
-						// 	None, Some(prereq_flag.version), &self.key);
-						// prereq_events.push(event);
-					} else {
-						return None;
-					}
+			// a flag that is its own prerequisite, directly or indirectly
+			if prereq_chain.contains(&prereq.key) {
+				return Err(ErrorKind::MalformedFlag);
+			}
+			let failed = match store.get(&prereq.key) {
+				Some(ref prereq_flag) => {
+					// An off prerequisite is still evaluated, so that it shows up in events, but it
+					// fails even if its off variation is the required one. Variations are compared
+					// by index, because two variations can have the same value.
+					let prereq_result = prereq_flag.evaluate_internal(context, store, prereq_chain, prereq_events)?;
+					prereq_events.push(event::feature_request(prereq_flag, context, &prereq_result,
+						None, Some(self.key.clone())));
+					!prereq_flag.on || prereq_result.variation_index != Some(prereq.variation)
 				}
 				None => {
 					// log: could not retrieve flag
-					return None;
+					true
 				}
+			};
+			if failed {
+				prereq_chain.pop();
+				return Ok(self.off_detail(EvaluationReason::PrerequisiteFailed { prerequisite_key: prereq.key.clone() }));
 			}
 		}
-		self.get_variation(self.evaluate_index(user))
+		if !self.prerequisites.is_empty() {
+			prereq_chain.pop();
+		}
+		Ok(match self.evaluate_index(context) {
+			Ok(Some((index, reason))) => match self.get_variation(Some(index)) {
+				Some(value) => EvaluationDetail { value, variation_index: Some(index), reason },
+				None => error_detail(ErrorKind::MalformedFlag)
+			},
+			Ok(None) => error_detail(ErrorKind::MalformedFlag),
+			Err(error_kind) => error_detail(error_kind)
+		})
 	}
 
 	fn get_variation(&self, index: Option<u32>) -> Option<Value> {
@@ -80,30 +173,44 @@ impl FeatureFlag {
 		}
 	}
 
-	fn get_off_variation_value(&self) -> Value {
-		match self.offVariation {
-			Some(n) => self.get_variation(Some(n)).unwrap_or(Value::Null),
-			None => Value::Null
+	fn off_detail(&self, reason: EvaluationReason) -> EvaluationDetail {
+		match self.offVariation.and_then(|n| self.get_variation(Some(n)).map(|value| (n, value))) {
+			Some((index, value)) => EvaluationDetail { value, variation_index: Some(index), reason },
+			None => EvaluationDetail { value: Value::Null, variation_index: None, reason }
 		}
 	}
 
-	fn evaluate_index(&self, user: &LDUser) -> Option<u32> {
-		for target in &self.targets {
-			for value in &target.values {
-				if *value == user.key {
-					return Some(target.variation);
+	// Err if a rule that had to be checked can't be evaluated.
+	fn evaluate_index(&self, context: &LDContext) -> Result<Option<(u32, EvaluationReason)>, ErrorKind> {
+		// Individual targets only apply to the "user" kind.
+		if let Some(user) = context.as_kind("user") {
+			for target in &self.targets {
+				if target.contains(user.key()) {
+					return Ok(Some((target.variation, EvaluationReason::TargetMatch)));
 				}
 			}
 		}
-		for rule in &self.rules {
-			if rule.matches_user(user) {
-				return rule.variation_index_for_user(user, &self.key, &self.salt);
+		for (i, rule) in self.rules.iter().enumerate() {
+			if rule.matches_user(context)? {
+				return Ok(rule.variation_index_for_user(context, &self.key, &self.salt)
+					.map(|(index, in_experiment)| (index, EvaluationReason::RuleMatch {
+						rule_index: i,
+						rule_id: rule.id.clone(),
+						in_experiment
+					})));
 			}
 		}
-		return self.fallthrough.variation_index_for_user(user, &self.key, &self.salt);
+		Ok(self.fallthrough.variation_index_for_user(context, &self.key, &self.salt)
+			.map(|(index, in_experiment)| (index, EvaluationReason::Fallthrough { in_experiment })))
 	}
 }
 
+// The result of an evaluation that went wrong. It has no variation, so the client returns the
+// caller's default value, as it does when the flag is off and has no off variation.
+fn error_detail(error_kind: ErrorKind) -> EvaluationDetail {
+	EvaluationDetail { value: Value::Null, variation_index: None, reason: EvaluationReason::Error { error_kind } }
+}
+
 #[derive(Clone)]
 #[derive(Deserialize)]
 #[derive(Debug)]
@@ -117,7 +224,23 @@ pub struct Prerequisite {
 #[derive(Debug)]
 pub struct Target {
 	pub values: Vec<String>,
-	pub variation: u32
+	pub variation: u32,
+	// the same keys as `values`, once the flag has been preprocessed
+	#[serde(skip)]
+	value_set: Option<HashSet<String>>
+}
+
+impl Target {
+	fn preprocess(&mut self) {
+		self.value_set = Some(self.values.iter().cloned().collect());
+	}
+
+	fn contains(&self, key: &str) -> bool {
+		match self.value_set {
+			Some(ref set) => set.contains(key),
+			None => self.values.iter().any(|v| v == key)
+		}
+	}
 }
 
 #[derive(Clone)]
@@ -125,24 +248,27 @@ pub struct Target {
 #[derive(Debug)]
 pub struct Rule {
 	// TODO: should share a trait with VariationOrRollout
+	pub id: Option<String>,
 	pub clauses: Vec<Clause>,
 	pub variation: Option<u32>,
-	pub rollout: Option<Rollout>
+	pub rollout: Option<Rollout>,
+	#[serde(default)]
+	pub trackEvents: bool
 }
 
 impl Rule {
-	pub fn matches_user(&self, user: &LDUser) -> bool {
+	pub fn matches_user(&self, context: &LDContext) -> Result<bool, ErrorKind> {
 		for clause in &self.clauses {
-			if !clause.matches_user(user) {
-				return false;
+			if !clause.matches_user(context)? {
+				return Ok(false);
 			}
 		}
-		true
+		Ok(true)
 	}
 
-	pub fn variation_index_for_user(&self, user: &LDUser, key: &String, salt: &String) -> Option<u32> {
+	pub fn variation_index_for_user(&self, context: &LDContext, key: &String, salt: &String) -> Option<(u32, bool)> {
 		// TODO: use a trait
-		variation_index(self.variation, &self.rollout, user, key, salt)
+		variation_index(self.variation, &self.rollout, context, key, salt)
 	}
 }
 
@@ -150,20 +276,87 @@ impl Rule {
 #[derive(Deserialize)]
 #[derive(Debug)]
 pub struct Clause {
+	// The kind of context the attribute belongs to; "user" if not specified.
+	pub contextKind: Option<String>,
 	pub attribute: String,
-	pub op: String,
+	pub op: Operator,
 	pub values: Vec<Value>,
-	pub negate: bool
+	pub negate: bool,
+	// the parsed attribute and values, once the flag has been preprocessed
+	#[serde(skip)]
+	attribute_ref: Option<AttributeRef>,
+	#[serde(skip)]
+	preprocessed: Option<PreprocessedValues>
+}
+
+// A clause's operator and values, converted ahead of time into the forms that evaluation needs.
+#[derive(Clone)]
+#[derive(Debug)]
+struct PreprocessedValues {
+	// looked up once, rather than searching the operator table for every evaluation
+	definition: &'static OperatorDefinition,
+	// the string values, for quick lookups in "in" clauses, which are often long lists of keys
+	strings: HashSet<String>,
+	// one for each of the clause's values
+	parsed: Vec<ParsedValue>
+}
+
+#[derive(Clone)]
+#[derive(Debug)]
+enum ParsedValue {
+	// the operator uses the JSON value as it is
+	Unparsed,
+	Regex(Regex),
+	DateTime(Timespec),
+	Semver(Version),
+	// the value isn't valid for the operator, so it never matches
+	Invalid
 }
 
 impl Clause {
-	fn matches_user(&self, user: &LDUser) -> bool {
-		let user_value = user.get_value_for_evaluation(&self.attribute);
-		match user_value {
-			Value::Null => false,
-			Value::Array(values) => self.maybe_negate(self.match_any_of_any(values)),
-			_ => self.maybe_negate(self.match_any(&user_value))
+	fn preprocess(&mut self) {
+		self.attribute_ref = Some(AttributeRef::new(&self.attribute));
+		if let Some(definition) = self.op.definition() {
+			self.preprocessed = Some(PreprocessedValues::new(definition, &self.values));
+		}
+	}
+
+	// Err if the operator is unknown, since the clause can't be evaluated.
+	fn matches_user(&self, context: &LDContext) -> Result<bool, ErrorKind> {
+		let computed;
+		let preprocessed = match self.preprocessed {
+			Some(ref p) => p,
+			None => match self.op.definition() {
+				Some(definition) => {
+					computed = PreprocessedValues::new(definition, &self.values);
+					&computed
+				},
+				None => return Err(ErrorKind::MalformedFlag)
+			}
+		};
+		let computed_ref;
+		let attr_ref = match self.attribute_ref {
+			Some(ref r) => r,
+			None => {
+				computed_ref = AttributeRef::new(&self.attribute);
+				&computed_ref
+			}
+		};
+		// "kind" refers to the kinds present in the context, whichever kind the clause names.
+		if attr_ref.is_top_level("kind") {
+			let kinds = context.kinds().iter().map(|k| json!(k)).collect();
+			return Ok(self.maybe_negate(self.match_any_of_any(kinds, preprocessed)));
 		}
+		let kind = self.contextKind.as_ref().map_or("user", |k| k.as_str());
+		let user_value = match context.as_kind(kind) {
+			Some(c) => c.get_value_for_ref(attr_ref),
+			None => Value::Null
+		};
+		Ok(match user_value {
+			Value::Null => false,
+			Value::Array(values) => self.maybe_negate(self.match_any_of_any(values, preprocessed)),
+			_ => self.maybe_negate(self.match_any(&user_value, preprocessed))
+		})
 	}
 
 	fn maybe_negate(&self, result: bool) -> bool {
@@ -174,25 +367,143 @@ impl Clause {
 		}
 	}
 
-	fn match_any(&self, user_value: &Value) -> bool {
-		for v in &self.values {
-			if apply_op(&self.op, user_value, v) {
+	fn match_any(&self, user_value: &Value, preprocessed: &PreprocessedValues) -> bool {
+		if self.op == Operator::In {
+			if let Value::String(ref s) = *user_value {
+				return preprocessed.strings.contains(s);
+			}
+		}
+		for (v, parsed) in self.values.iter().zip(&preprocessed.parsed) {
+			if (preprocessed.definition.apply)(user_value, v, parsed) {
 				return true;
 			}
 		}
-		return false;
+		false
 	}
 
-	fn match_any_of_any(&self, user_values: Vec<Value>) -> bool {
+	fn match_any_of_any(&self, user_values: Vec<Value>, preprocessed: &PreprocessedValues) -> bool {
 		for uv in &user_values {
-			if self.match_any(uv) {
+			if self.match_any(uv, preprocessed) {
 				return true;
 			}
 		}
-		return false;
+		false
 	}
 }
 
+impl PreprocessedValues {
+	fn new(definition: &'static OperatorDefinition, values: &[Value]) -> PreprocessedValues {
+		PreprocessedValues {
+			definition,
+			strings: values.iter().filter_map(|v| v.as_str()).map(|s| s.to_owned()).collect(),
+			parsed: values.iter().map(|v| (definition.parse)(v).unwrap_or(ParsedValue::Invalid)).collect()
+		}
+	}
+}
+
+// An operator that this version of the SDK doesn't know is kept as Unknown, rather than failing to
+// parse the whole flag, so that only evaluations which reach a clause using it give an error.
+#[derive(Clone)]
+#[derive(Deserialize)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[serde(from = "String")]
+pub enum Operator {
+	In,
+	EndsWith,
+	StartsWith,
+	Matches,
+	Contains,
+	LessThan,
+	LessThanOrEqual,
+	GreaterThan,
+	GreaterThanOrEqual,
+	Before,
+	After,
+	SemVerEqual,
+	SemVerLessThan,
+	SemVerGreaterThan,
+	Unknown(String)
+}
+
+impl Operator {
+	// None if the operator is unknown.
+	fn definition(&self) -> Option<&'static OperatorDefinition> {
+		OPERATORS.iter().find(|d| d.op == *self)
+	}
+}
+
+impl From<String> for Operator {
+	fn from(name: String) -> Operator {
+		match OPERATORS.iter().find(|d| d.name == name) {
+			Some(d) => d.op.clone(),
+			None => Operator::Unknown(name)
+		}
+	}
+}
+
+// Everything that evaluation needs to know about an operator. Adding an operator only takes a new
+// variant of Operator and an entry in OPERATORS.
+#[derive(Debug)]
+struct OperatorDefinition {
+	op: Operator,
+	// the name used in the flag JSON
+	name: &'static str,
+	// converts a clause value ahead of time; None if the value can never match
+	parse: fn(&Value) -> Option<ParsedValue>,
+	// takes the user value, the clause value, and the clause value as converted by parse
+	apply: fn(&Value, &Value, &ParsedValue) -> bool
+}
+
+// TODO: segmentMatch. Segments can include other segments, so when they are supported they will
+// need the same cycle detection as prerequisites.
+static OPERATORS: [OperatorDefinition; 14] = [
+	OperatorDefinition { op: Operator::In, name: "in", parse: unparsed,
+		apply: |uv, cv, _| values_equal(uv, cv) },
+	OperatorDefinition { op: Operator::EndsWith, name: "endsWith", parse: unparsed,
+		apply: |uv, cv, _| apply_string_op(uv, cv, &(|us: &String, cs: &String| us.ends_with(cs))) },
+	OperatorDefinition { op: Operator::StartsWith, name: "startsWith", parse: unparsed,
+		apply: |uv, cv, _| apply_string_op(uv, cv, &(|us: &String, cs: &String| us.starts_with(cs))) },
+	OperatorDefinition { op: Operator::Matches, name: "matches",
+		parse: |v| v.as_str().and_then(|s| Regex::new(s).ok()).map(ParsedValue::Regex),
+		apply: |uv, _, parsed| match (uv, parsed) {
+			(Value::String(us), ParsedValue::Regex(re)) => re.is_match(us),
+			_ => false
+		} },
+	OperatorDefinition { op: Operator::Contains, name: "contains", parse: unparsed,
+		apply: |uv, cv, _| apply_string_op(uv, cv, &(|us: &String, cs: &String| us.contains(cs))) },
+	OperatorDefinition { op: Operator::LessThan, name: "lessThan", parse: unparsed,
+		apply: |uv, cv, _| apply_numeric_op(uv, cv, &(|un: f64, cn: f64| un < cn)) },
+	OperatorDefinition { op: Operator::LessThanOrEqual, name: "lessThanOrEqual", parse: unparsed,
+		apply: |uv, cv, _| apply_numeric_op(uv, cv, &(|un: f64, cn: f64| un <= cn)) },
+	OperatorDefinition { op: Operator::GreaterThan, name: "greaterThan", parse: unparsed,
+		apply: |uv, cv, _| apply_numeric_op(uv, cv, &(|un: f64, cn: f64| un > cn)) },
+	OperatorDefinition { op: Operator::GreaterThanOrEqual, name: "greaterThanOrEqual", parse: unparsed,
+		apply: |uv, cv, _| apply_numeric_op(uv, cv, &(|un: f64, cn: f64| un >= cn)) },
+	OperatorDefinition { op: Operator::Before, name: "before", parse: parse_date_time_value,
+		apply: |uv, _, parsed| apply_time_op(uv, parsed, &(|ut: Timespec, ct: Timespec| ut < ct)) },
+	OperatorDefinition { op: Operator::After, name: "after", parse: parse_date_time_value,
+		apply: |uv, _, parsed| apply_time_op(uv, parsed, &(|ut: Timespec, ct: Timespec| ut > ct)) },
+	OperatorDefinition { op: Operator::SemVerEqual, name: "semVerEqual", parse: parse_semver_value,
+		apply: |uv, _, parsed| apply_semver_op(uv, parsed, &(|uv: &Version, cv: &Version| uv == cv)) },
+	OperatorDefinition { op: Operator::SemVerLessThan, name: "semVerLessThan", parse: parse_semver_value,
+		apply: |uv, _, parsed| apply_semver_op(uv, parsed, &(|uv: &Version, cv: &Version| uv < cv)) },
+	OperatorDefinition { op: Operator::SemVerGreaterThan, name: "semVerGreaterThan", parse: parse_semver_value,
+		apply: |uv, _, parsed| apply_semver_op(uv, parsed, &(|uv: &Version, cv: &Version| uv > cv)) }
+];
+
+fn unparsed(_: &Value) -> Option<ParsedValue> {
+	Some(ParsedValue::Unparsed)
+}
+
+fn parse_date_time_value(value: &Value) -> Option<ParsedValue> {
+	parse_date_time(value).map(ParsedValue::DateTime)
+}
+
+fn parse_semver_value(value: &Value) -> Option<ParsedValue> {
+	parse_semver(value).map(ParsedValue::Semver)
+}
+
 #[derive(Clone)]
 #[derive(Deserialize)]
 #[derive(Debug)]
@@ -202,56 +513,85 @@ pub struct VariationOrRollout {
 }
 
 impl VariationOrRollout {
-	pub fn variation_index_for_user(&self, user: &LDUser, key: &String, salt: &String) -> Option<u32> {
-		variation_index(self.variation, &self.rollout, user, key, salt)
+	pub fn variation_index_for_user(&self, context: &LDContext, key: &String, salt: &String) -> Option<(u32, bool)> {
+		variation_index(self.variation, &self.rollout, context, key, salt)
 	}
 }
 
+// Returns the variation index, and whether the context is part of an experiment.
 fn variation_index(variation: Option<u32>, rollout: &Option<Rollout>,
-				   user: &LDUser, key: &String, salt: &String) -> Option<u32> {
+				   context: &LDContext, key: &String, salt: &String) -> Option<(u32, bool)> {
 	match variation {
-		Some(n) => Some(n),
+		Some(n) => Some((n, false)),
 		None => match rollout {
-			&Some(ref roll) => {
-				let bucket_by = roll.bucketBy.clone().unwrap_or(String::from("key"));
-				let bucket = bucket_user(user, key, &bucket_by, salt);
+			Some(roll) => {
+				let kind = roll.contextKind.as_ref().map_or("user", |k| k.as_str());
+				let bucket_context = context.as_kind(kind);
+				let bucket = match bucket_context {
+					Some(c) => bucket_user(c, key, salt, roll),
+					None => 0.0
+				};
 				let mut sum = 0.0;
 				for wv in &roll.variations {
-					sum += wv.weight as f32 / 100000.0;
+					sum += wv.weight as f64 / 100000.0;
 					if bucket < sum {
-						return Some(wv.variation)
+						return Some((wv.variation, roll.is_experiment() && !wv.untracked && bucket_context.is_some()));
 					}
 				}
-				None
+				// The weights should add up to 100000, but if they don't and this context's bucket
+				// is past the end, it goes in the last bucket.
+				roll.variations.last()
+					.map(|wv| (wv.variation, roll.is_experiment() && !wv.untracked && bucket_context.is_some()))
 			},
 			&None => None
 		}
 	}
 }
 
-fn bucket_user(user: &LDUser, key: &String, bucket_by: &String, salt: &String) -> f32 {
-	let user_value = user.get_value_for_evaluation(bucket_by);
+// Assigns the context a number in [0, 1) based on a hash of its attribute value, using the same
+// algorithm as every other LaunchDarkly SDK so that contexts land in the same bucket everywhere.
+// Experiments always bucket by key, and never use the secondary key.
+fn bucket_user(context: &LDContext, key: &String, salt: &String, rollout: &Rollout) -> f64 {
+	let is_experiment = rollout.is_experiment();
+	let computed_ref;
+	let bucket_by = match rollout.bucket_by_ref {
+		Some(ref r) => r,
+		None => {
+			computed_ref = rollout.make_bucket_by_ref();
+			&computed_ref
+		}
+	};
+	let user_value = context.get_value_for_ref(bucket_by);
 	let maybe_hash_input = get_bucketable_string_value(user_value);
 	match maybe_hash_input {
 		Some(hash_input) => {
-			let hash_str = match user.secondary.clone() {
-				Some(sec) => format!("{}.{}", hash_input, sec),
-				None => hash_input
+			let hash_str = match context.get_value("secondary") {
+				Value::String(ref sec) if !is_experiment => format!("{}.{}", hash_input, sec),
+				_ => hash_input
+			};
+			// A seed replaces the flag key and salt, so that a set of flags can share buckets.
+			let prefix = match rollout.seed {
+				Some(seed) => seed.to_string(),
+				None => format!("{}.{}", key, salt)
 			};
 			let mut sha = sha1::Sha1::new();
-			sha.update(format!("{}.{}.{}", key, salt, hash_str).as_bytes());
+			sha.update(format!("{}.{}", prefix, hash_str).as_bytes());
 			let hash_out: String = sha.digest().to_string().chars().take(15).collect();
-			let long_val = i32::from_str_radix(&hash_out, 16).unwrap_or(0);
-			(long_val as f32) / 100000.0
+			let long_val = u64::from_str_radix(&hash_out, 16).unwrap_or(0);
+			(long_val as f64) / (0xFFFFFFFFFFFFFFFu64 as f64)
 		}
 		None => 0.0
 	}
 }
 
+// Only strings and integers can be bucketed by; anything else puts the context in bucket 0.
 fn get_bucketable_string_value(user_value: Value) -> Option<String> {
 	match user_value {
 		Value::String(s) => Some(s),
-		Value::Number(n) => n.as_i64().map(|n| n.to_string()),
+		Value::Number(n) => match n.as_i64() {
+			Some(i) => Some(i.to_string()),
+			None => n.as_f64().filter(|f| f.fract() == 0.0).map(|f| (f as i64).to_string())
+		},
 		_ => None
 	}
 }
@@ -260,8 +600,34 @@ fn get_bucketable_string_value(user_value: Value) -> Option<String> {
 #[derive(Deserialize)]
 #[derive(Debug)]
 pub struct Rollout {
+	// "experiment" if evaluations should be tracked as part of an experiment; otherwise "rollout"
+	// or not specified.
+	pub kind: Option<String>,
+	pub seed: Option<i64>,
 	pub variations: Vec<WeightedVariation>,
-	pub bucketBy: Option<String>
+	pub bucketBy: Option<String>,
+	// The kind of context to bucket by; "user" if not specified.
+	pub contextKind: Option<String>,
+	// the attribute to bucket by, once the flag has been preprocessed
+	#[serde(skip)]
+	bucket_by_ref: Option<AttributeRef>
+}
+
+impl Rollout {
+	pub fn is_experiment(&self) -> bool {
+		self.kind.as_ref().is_some_and(|k| k == "experiment")
+	}
+
+	fn preprocess(&mut self) {
+		self.bucket_by_ref = Some(self.make_bucket_by_ref());
+	}
+
+	fn make_bucket_by_ref(&self) -> AttributeRef {
+		match self.bucketBy {
+			Some(ref attr) if !self.is_experiment() => AttributeRef::new(attr),
+			_ => AttributeRef::new("key")
+		}
+	}
 }
 
 #[derive(Clone)]
@@ -269,98 +635,187 @@ pub struct Rollout {
 #[derive(Debug)]
 pub struct WeightedVariation {
 	pub variation: u32,
-	pub weight: u32
-}
-
-fn apply_op(op: &String, user_value: &Value, clause_value: &Value) -> bool {
-	match op.as_ref() {
-		"in" => user_value == clause_value,
-		"endsWith" => apply_string_op(user_value, clause_value, &(|us: &String, cs: &String| us.starts_with(cs))),
-		"startsWith" => apply_string_op(user_value, clause_value, &(|us: &String, cs: &String| us.ends_with(cs))),
-		"matches" => apply_string_op(user_value, clause_value, &regex_match_op),
-		"contains" => apply_string_op(user_value, clause_value, &(|us: &String, cs: &String| us.contains(cs))),
-		"lessThan" => apply_numeric_op(user_value, clause_value, &(|un: f64, cn: f64| un < cn)),
-		"lessThanOrEqual" => apply_numeric_op(user_value, clause_value, &(|un: f64, cn: f64| un <= cn)),
-		"greaterThan" => apply_numeric_op(user_value, clause_value, &(|un: f64, cn: f64| un > cn)),
-		"greaterThanOrEqual" => apply_numeric_op(user_value, clause_value, &(|un: f64, cn: f64| un >= cn)),
-		"before" => apply_time_op(user_value, clause_value, &(|ut: Timespec, ct: Timespec| ut < ct)),
-		"after" => apply_time_op(user_value, clause_value, &(|ut: Timespec, ct: Timespec| ut > ct)),
-		"semVerEqual" => apply_semver_op(user_value, clause_value, &(|uv: Version, cv: Version| uv == cv)),
-		"semVerLessThan" => apply_semver_op(user_value, clause_value, &(|uv: Version, cv: Version| uv < cv)),
-		"semVerGreaterThan" => apply_semver_op(user_value, clause_value, &(|uv: Version, cv: Version| uv > cv)),
-		_ => false
-	}
+	pub weight: u32,
+	// Contexts allocated to an untracked variation aren't counted in the experiment.
+	#[serde(default)]
+	pub untracked: bool
 }
 
-fn apply_string_op(user_value: &Value, clause_value: &Value, f: &Fn(&String, &String) -> bool) -> bool {
+fn apply_string_op(user_value: &Value, clause_value: &Value, f: &dyn Fn(&String, &String) -> bool) -> bool {
 	match user_value {
-		&Value::String(ref us) => match clause_value {
-			&Value::String(ref cs) => f(us, cs),
+		Value::String(us) => match clause_value {
+			Value::String(cs) => f(us, cs),
 			_ => false
 		},
 		_ => false
 	}
 }
 
-fn apply_numeric_op(user_value: &Value, clause_value: &Value, f: &Fn(f64, f64) -> bool) -> bool {
+// Numbers are equal if they have the same value, so 1 and 1.0 are the same, as in the other SDKs.
+fn values_equal(a: &Value, b: &Value) -> bool {
+	match (a, b) {
+		(Value::Number(an), Value::Number(bn)) => an.as_f64() == bn.as_f64(),
+		(Value::Array(aa), Value::Array(ba)) =>
+			aa.len() == ba.len() && aa.iter().zip(ba).all(|(av, bv)| values_equal(av, bv)),
+		(Value::Object(ao), Value::Object(bo)) =>
+			ao.len() == bo.len() && ao.iter().all(|(k, av)| bo.get(k).is_some_and(|bv| values_equal(av, bv))),
+		_ => a == b
+	}
+}
+
+fn apply_numeric_op(user_value: &Value, clause_value: &Value, f: &dyn Fn(f64, f64) -> bool) -> bool {
 	match user_value {
-		&Value::Number(ref un) => match clause_value {
-			&Value::Number(ref cn) => f(un.as_f64().unwrap_or(0.0), cn.as_f64().unwrap_or(0.0)),
+		Value::Number(un) => match clause_value {
+			Value::Number(cn) => f(un.as_f64().unwrap_or(0.0), cn.as_f64().unwrap_or(0.0)),
 			_ => false
 		},
 		_ => false
 	}
 }
 
-fn apply_time_op(user_value: &Value, clause_value: &Value, f: &Fn(Timespec, Timespec) -> bool) -> bool {
+fn apply_time_op(user_value: &Value, parsed: &ParsedValue, f: &dyn Fn(Timespec, Timespec) -> bool) -> bool {
 	match parse_date_time(user_value) {
-		Some(ut) => match parse_date_time(clause_value) {
-			Some(ct) => f(ut, ct),
+		Some(ut) => match *parsed {
+			ParsedValue::DateTime(ct) => f(ut, ct),
 			_ => false
 		},
 		_ => false
 	}
 }
 
-fn apply_semver_op(user_value: &Value, clause_value: &Value, f: &Fn(Version, Version) -> bool) -> bool {
+fn apply_semver_op(user_value: &Value, parsed: &ParsedValue, f: &dyn Fn(&Version, &Version) -> bool) -> bool {
 	match parse_semver(user_value) {
-		Some(uv) => match parse_semver(clause_value) {
-			Some(cv) => f(uv, cv),
+		Some(uv) => match *parsed {
+			ParsedValue::Semver(ref cv) => f(&uv, cv),
 			_ => false
 		},
 		_ => false
 	}
 }
 
-fn regex_match_op(us: &String, cs: &String) -> bool {
-	match Regex::new(cs) {
-		Ok(re) => re.is_match(us),
-		_ => false
-	}
-}
-
+// Dates are either RFC 3339 timestamps or milliseconds since the epoch, which may have a
+// fractional part.
 fn parse_date_time(value: &Value) -> Option<Timespec> {
 	match value {
-		&Value::String(ref s) => match strptime(&s, &String::from("%FT%TZ")) {
-			Ok(tm) => Some(tm.to_timespec()),
-			_ => None
-		},
-		&Value::Number(ref un) => match un.as_i64() {
-			Some(n) => Some(Timespec::new(n / 1000, ((n % 1000) * 1000000) as i32)),
-			_ => None
+		Value::String(s) => parse_rfc3339(s),
+		Value::Number(un) => match un.as_i64() {
+			Some(n) => Some(Timespec::new(n.div_euclid(1000), (n.rem_euclid(1000) * 1000000) as i32)),
+			None => un.as_f64().and_then(|f| {
+				let sec = (f / 1000.0).floor();
+				// too far from the epoch for a Timespec
+				if !(sec >= i64::MIN as f64 && sec < i64::MAX as f64) {
+					return None;
+				}
+				// for large values, rounding can leave the remainder just outside [0, 1000)
+				let nsec = (f.rem_euclid(1000.0) * 1000000.0).round().clamp(0.0, 999999999.0);
+				Some(Timespec::new(sec as i64, nsec as i32))
+			})
 		},
 		_ => None
 	}
 }
 
+// Parses timestamps like "2026-03-01T10:00:00Z" and "2026-03-01T10:00:00.123+02:00". strptime
+// can't be used for this, because it ignores trailing text and doesn't check the day of the month.
+fn parse_rfc3339(s: &str) -> Option<Timespec> {
+	let b = s.as_bytes();
+	if !s.is_ascii() || b.len() < 20 || b[4] != b'-' || b[7] != b'-' || (b[10] != b'T' && b[10] != b't') || b[13] != b':' || b[16] != b':' {
+		return None;
+	}
+	let year = parse_digits(&s[0..4])?;
+	let month = parse_digits(&s[5..7])?;
+	let day = parse_digits(&s[8..10])?;
+	let hour = parse_digits(&s[11..13])?;
+	let minute = parse_digits(&s[14..16])?;
+	let second = parse_digits(&s[17..19])?;
+	if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) || hour > 23 || minute > 59 || second > 59 {
+		return None;
+	}
+
+	let mut rest = &s[19..];
+	let mut nsec = 0;
+	if rest.starts_with('.') {
+		let digits = rest[1..].find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len() - 1);
+		if digits == 0 {
+			return None;
+		}
+		// anything after nanoseconds is dropped
+		for (i, c) in rest[1..1 + digits].bytes().take(9).enumerate() {
+			nsec += (c - b'0') as i32 * 10i32.pow(8 - i as u32);
+		}
+		rest = &rest[1 + digits..];
+	}
+
+	let offset = match rest {
+		"Z" | "z" => 0,
+		_ => {
+			let ob = rest.as_bytes();
+			if ob.len() != 6 || (ob[0] != b'+' && ob[0] != b'-') || ob[3] != b':' {
+				return None;
+			}
+			let (offset_hours, offset_minutes) = (parse_digits(&rest[1..3])?, parse_digits(&rest[4..6])?);
+			if offset_hours > 23 || offset_minutes > 59 {
+				return None;
+			}
+			let sign = if ob[0] == b'-' { -1 } else { 1 };
+			sign * (offset_hours * 3600 + offset_minutes * 60)
+		}
+	};
+
+	let sec = days_since_epoch(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset;
+	Some(Timespec::new(sec, nsec))
+}
+
+fn parse_digits(s: &str) -> Option<i64> {
+	if s.is_empty() || !s.bytes().all(|c| c.is_ascii_digit()) {
+		return None;
+	}
+	s.parse().ok()
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+	match month {
+		2 => if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 { 29 } else { 28 },
+		4 | 6 | 9 | 11 => 30,
+		_ => 31
+	}
+}
+
+// The number of days from 1970-01-01 to the given date in the proleptic Gregorian calendar, for
+// years from 0 to 9999.
+fn days_since_epoch(year: i64, month: i64, day: i64) -> i64 {
+	// count years from March, so that the leap day is at the end of the year
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = (if year >= 0 { year } else { year - 399 }) / 400;
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	era * 146097 + day_of_era - 719468
+}
+
 fn parse_semver(value: &Value) -> Option<Version> {
 	match value {
-		&Value::String(ref s) => match Version::parse(s) {
-			Ok(sv) => Some(sv),
-			_ => None
-		},
+		Value::String(s) => parse_lenient_semver(s),
 		_ => None
 	}
-	// TODO: LaunchDarkly allows semvers to omit the minor and/or patch versions, so if we
-	// get a parse error here, we should try adding ".0" or ".0.0" at the appropriate point.
+}
+
+// LaunchDarkly allows the minor and patch versions to be omitted, so "1" and "1.2-beta" mean
+// "1.0.0" and "1.2.0-beta". Build metadata is dropped, since it doesn't affect precedence.
+fn parse_lenient_semver(s: &str) -> Option<Version> {
+	let mut version = match Version::parse(s) {
+		Ok(v) => v,
+		Err(_) => {
+			let numbers_end = s.find(&['-', '+'][..]).unwrap_or(s.len());
+			let (numbers, rest) = s.split_at(numbers_end);
+			let parts: Vec<&str> = numbers.split('.').collect();
+			if parts.len() >= 3 || parts.iter().any(|p| p.is_empty() || !p.chars().all(|c| c.is_ascii_digit())) {
+				return None;
+			}
+			let padded = format!("{}{}{}", numbers, ".0".repeat(3 - parts.len()), rest);
+			Version::parse(&padded).ok()?
+		}
+	};
+	version.build.clear();
+	Some(version)
 }
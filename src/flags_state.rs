@@ -0,0 +1,145 @@
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use evaluation::{EvaluationDetail, EvaluationReason};
+use event::current_time_millis;
+use flag::FeatureFlag;
+
+
+// Options for LDClient::all_flags_state. By default, all flags are included, without reasons.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Default)]
+pub struct FlagsStateOptions {
+	// Only include flags that are available to client-side SDKs.
+	pub client_side_only: bool,
+	// Include the evaluation reason for every flag, not just for those in experiments.
+	pub with_reasons: bool,
+	// Leave out the version and reason of flags whose evaluations aren't tracked, which makes the
+	// bootstrap data smaller; the client-side SDK only needs them for analytics events.
+	pub details_only_for_tracked_flags: bool
+}
+
+impl FlagsStateOptions {
+	pub fn with_client_side_only(&self, value: bool) -> FlagsStateOptions {
+		FlagsStateOptions { client_side_only: value, ..self.clone() }
+	}
+
+	pub fn with_reasons(&self, value: bool) -> FlagsStateOptions {
+		FlagsStateOptions { with_reasons: value, ..self.clone() }
+	}
+
+	pub fn with_details_only_for_tracked_flags(&self, value: bool) -> FlagsStateOptions {
+		FlagsStateOptions { details_only_for_tracked_flags: value, ..self.clone() }
+	}
+}
+
+// The values of all flags for one context, with the metadata that a client-side SDK needs to
+// send analytics events for them. to_json gives the format that the JavaScript SDK accepts as
+// bootstrap data.
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct FeatureFlagsState {
+	values: HashMap<String, Value>,
+	metadata: HashMap<String, FlagMetadata>,
+	// false if the client had no flag data, in which case the state is empty
+	valid: bool
+}
+
+#[derive(Clone)]
+#[derive(Debug)]
+struct FlagMetadata {
+	variation: Option<u32>,
+	// None if details were left out
+	version: Option<u32>,
+	reason: Option<EvaluationReason>,
+	track_events: bool,
+	// the reason must be sent in events, because the flag is part of an experiment
+	track_reason: bool,
+	debug_events_until_date: Option<u64>
+}
+
+impl FeatureFlagsState {
+	pub fn invalid() -> FeatureFlagsState {
+		FeatureFlagsState { values: HashMap::new(), metadata: HashMap::new(), valid: false }
+	}
+
+	pub fn new() -> FeatureFlagsState {
+		FeatureFlagsState { values: HashMap::new(), metadata: HashMap::new(), valid: true }
+	}
+
+	pub fn add_flag(&mut self, flag: &FeatureFlag, detail: EvaluationDetail, options: &FlagsStateOptions) {
+		let track_reason = flag.is_experiment(&detail.reason);
+		let track_events = flag.trackEvents || track_reason;
+		let debugging = flag.debugEventsUntilDate.is_some_and(|date| date > current_time_millis());
+		let with_details = !options.details_only_for_tracked_flags || track_events || debugging;
+		self.metadata.insert(flag.key.clone(), FlagMetadata {
+			variation: detail.variation_index,
+			version: if with_details { Some(flag.version) } else { None },
+			reason: if with_details && (options.with_reasons || track_reason) { Some(detail.reason) } else { None },
+			track_events,
+			track_reason,
+			debug_events_until_date: flag.debugEventsUntilDate
+		});
+		self.values.insert(flag.key.clone(), detail.value);
+	}
+
+	pub fn is_valid(&self) -> bool {
+		self.valid
+	}
+
+	pub fn get_flag_value(&self, key: &str) -> Option<&Value> {
+		self.values.get(key)
+	}
+
+	// None if the flag doesn't exist, or if reasons weren't requested.
+	pub fn get_flag_reason(&self, key: &str) -> Option<&EvaluationReason> {
+		self.metadata.get(key).and_then(|m| m.reason.as_ref())
+	}
+
+	pub fn to_values_map(&self) -> HashMap<String, Value> {
+		self.values.clone()
+	}
+
+	// The flag values keyed by flag key, plus "$flagsState" with each flag's metadata and "$valid".
+	pub fn to_json(&self) -> Value {
+		let mut out = Map::new();
+		for (key, value) in &self.values {
+			out.insert(key.clone(), value.clone());
+		}
+		let mut flags_state = Map::new();
+		for (key, m) in &self.metadata {
+			let mut meta = Map::new();
+			if let Some(variation) = m.variation {
+				meta.insert("variation".to_owned(), json!(variation));
+			}
+			if let Some(version) = m.version {
+				meta.insert("version".to_owned(), json!(version));
+			}
+			if let Some(ref reason) = m.reason {
+				meta.insert("reason".to_owned(), reason.to_json());
+			}
+			if m.track_events {
+				meta.insert("trackEvents".to_owned(), json!(true));
+			}
+			if m.track_reason {
+				meta.insert("trackReason".to_owned(), json!(true));
+			}
+			if let Some(date) = m.debug_events_until_date {
+				meta.insert("debugEventsUntilDate".to_owned(), json!(date));
+			}
+			flags_state.insert(key.clone(), Value::Object(meta));
+		}
+		out.insert("$flagsState".to_owned(), Value::Object(flags_state));
+		out.insert("$valid".to_owned(), json!(self.valid));
+		Value::Object(out)
+	}
+}
+
+impl Default for FeatureFlagsState {
+	fn default() -> FeatureFlagsState {
+		FeatureFlagsState::new()
+	}
+}
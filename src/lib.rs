@@ -1,10 +1,16 @@
 
+
+extern crate flate2;
+extern crate hmac;
 extern crate regex;
 extern crate reqwest;
 extern crate semver;
 extern crate serde;
 extern crate sha1;
+extern crate sha2;
 extern crate time;
+extern crate toml;
+extern crate uuid;
 
 #[macro_use]
 extern crate serde_json;
@@ -17,12 +23,28 @@ pub mod config;
 #[allow(non_snake_case)]
 pub mod flag;
 
+pub mod evaluation;
+
+pub mod flags_state;
+
 #[allow(non_snake_case)]
 pub mod user;
 
+pub mod user_filter;
+
+pub mod context;
+
+pub mod attribute_ref;
+
 #[allow(non_snake_case)]
 pub mod event;
 
+pub mod event_processor;
+
+pub mod lru_cache;
+
+pub mod diagnostics;
+
 pub mod feature_store;
 
 pub mod requestor;
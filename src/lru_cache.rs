@@ -0,0 +1,63 @@
+
+use std::collections::{BTreeMap, HashMap};
+
+
+// A set of strings that remembers at most `capacity` of them, forgetting whichever was least
+// recently used when it's full. The event processor uses this to remember which users it has
+// already sent in an index event.
+pub struct LruCache {
+	capacity: usize,
+	// each key maps to the time it was last used; `by_time` is the reverse mapping
+	by_key: HashMap<String, u64>,
+	by_time: BTreeMap<u64, String>,
+	clock: u64
+}
+
+impl LruCache {
+
+	pub fn new(capacity: usize) -> LruCache {
+		LruCache {
+			capacity,
+			by_key: HashMap::new(),
+			by_time: BTreeMap::new(),
+			clock: 0
+		}
+	}
+
+	// Marks the key as most recently used. Returns true if it was already in the cache.
+	pub fn notice(&mut self, key: &str) -> bool {
+		if self.capacity == 0 {
+			return false;
+		}
+		self.clock += 1;
+		if let Some(time) = self.by_key.get_mut(key) {
+			let owned = self.by_time.remove(time).unwrap();
+			*time = self.clock;
+			self.by_time.insert(self.clock, owned);
+			return true;
+		}
+		if self.by_key.len() >= self.capacity {
+			let oldest = self.by_time.keys().next().cloned();
+			if let Some(t) = oldest {
+				let evicted = self.by_time.remove(&t).unwrap();
+				self.by_key.remove(&evicted);
+			}
+		}
+		self.by_key.insert(key.to_owned(), self.clock);
+		self.by_time.insert(self.clock, key.to_owned());
+		false
+	}
+
+	pub fn clear(&mut self) {
+		self.by_key.clear();
+		self.by_time.clear();
+	}
+
+	pub fn len(&self) -> usize {
+		self.by_key.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.by_key.is_empty()
+	}
+}
@@ -12,10 +12,10 @@ use ldclient::config::LDConfig;
 use ldclient::user::LDUser;
 
 fn main() {
-	let config = LDConfig::default()
-        .with_polling_interval_millis(5000);
-    let sdk_key = "sdk-03947004-7d32-4878-a80b-ade2314efece".to_owned();
-    let client = LDClient::new(sdk_key, config);
+	let config = LDConfig::from_env()
+		.unwrap_or_else(|e| panic!("invalid configuration: {}", e));
+	let client = LDClient::from_config(config)
+		.unwrap_or_else(|e| panic!("unable to create client (is LD_SDK_KEY set?): {}", e));
     
     let user = LDUser::new(String::from("bozo"))
         .with_email(Some(String::from("test@example.com")));
@@ -1,11 +1,16 @@
 
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::sync::mpsc;
-use std::sync::mpsc::{Sender, Receiver};
+use std::sync::mpsc::{Sender, Receiver, RecvTimeoutError};
 use std::thread;
 use std::thread::JoinHandle;
 use std::time;
 
+use reqwest;
+
+use diagnostics::DiagnosticsRecorder;
+use event::current_time_millis;
+
 use feature_store::FeatureStore;
 use requestor::Requestor;
 
@@ -13,51 +18,73 @@ use requestor::Requestor;
 pub struct PollingProcessor {
 	task: Arc<PollingProcessorTask>,
 	handle: Option<JoinHandle<()>>,
-	ready: Receiver<()>
+	// dropping this tells the polling thread to stop
+	stop: Option<Sender<()>>
 }
 
 struct PollingProcessorTask {
 	requestor: Requestor,
 	interval: u64,
-	store: Arc<Mutex<FeatureStore>>,
-	ready: Mutex<Sender<()>>
+	store: Arc<FeatureStore>,
+	diagnostics: Arc<DiagnosticsRecorder>
 }
 
 impl PollingProcessor {
-	pub fn new(sdk_key: String, store: &Arc<Mutex<FeatureStore>>, base_uri: &String, interval: u64) -> PollingProcessor {
-		let (tx, rx): (Sender<()>, Receiver<()>) = mpsc::channel();
+	pub fn new(sdk_key: String, store: &Arc<FeatureStore>, base_uri: &str, interval: u64,
+			   http_client: reqwest::Client, diagnostics: &Arc<DiagnosticsRecorder>) -> PollingProcessor {
 		let task: Arc<PollingProcessorTask> = Arc::new(PollingProcessorTask {
-			requestor: Requestor::new(sdk_key, base_uri),
-			interval: interval,
+			requestor: Requestor::new(sdk_key, base_uri, http_client),
+			interval,
 			store: store.clone(),
-			ready: Mutex::new(tx)
+			diagnostics: diagnostics.clone()
 		});
 		PollingProcessor {
-			task: task,
+			task,
 			handle: None,
-			ready: rx
+			stop: None
 		}
 	}
 
-	pub fn start(&mut self) -> &Receiver<()> {
+	// Returns a channel that receives a message once flags have been received.
+	pub fn start(&mut self) -> Receiver<()> {
 		let task = self.task.clone();
+		let (ready_tx, ready_rx): (Sender<()>, Receiver<()>) = mpsc::channel();
+		let (stop_tx, stop_rx): (Sender<()>, Receiver<()>) = mpsc::channel();
 		let handle = thread::spawn(move || {
+			let mut initialized = false;
 			loop {
-				match task.requestor.get_all_flags() {
-					Ok(flags) => {
-						let mut store = task.store.lock().unwrap();
-						store.init(&flags);
-						// signal that we're ready
-						task.ready.lock().unwrap().send(()).unwrap();
-					},
-					_ => () // TODO: error logging
+				let started = time::Instant::now();
+				let timestamp = current_time_millis();
+				let result = task.requestor.get_all_flags();
+				if !initialized {
+					let millis = started.elapsed().as_millis() as u64;
+					task.diagnostics.record_stream_init(timestamp, millis, result.is_err());
+				}
+				// TODO: error logging
+				if let Ok(flags) = result {
+					task.store.init(flags);
+					if !initialized {
+						initialized = true;
+						// signal that we're ready; the client may have stopped waiting
+						let _ = ready_tx.send(());
+					}
+				}
+				match stop_rx.recv_timeout(time::Duration::from_millis(task.interval)) {
+					Err(RecvTimeoutError::Timeout) => (),
+					_ => return
 				}
-				thread::sleep(time::Duration::from_millis(task.interval));
 			}
 		});
 		self.handle = Some(handle);
-		&self.ready
+		self.stop = Some(stop_tx);
+		ready_rx
 	}
 
-	// TODO: need a way to stop the task.
+	// Stops polling, waiting for a request that is in progress to finish.
+	pub fn stop(&mut self) {
+		self.stop = None;
+		if let Some(handle) = self.handle.take() {
+			let _ = handle.join();
+		}
+	}
 }
@@ -1,9 +1,12 @@
 
 use std::collections::HashMap;
+use std::time::Duration;
 
 use reqwest;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use serde_json;
 
+use config::LDConfig;
 use flag::FeatureFlag;
 
 
@@ -18,20 +21,101 @@ struct AllData {
 	pub flags: HashMap<String, FeatureFlag>
 }
 
+pub const USER_AGENT: &str = concat!("RustClient/", env!("CARGO_PKG_VERSION"));
+
+// Builds the HTTP client that all of the SDK's network components share. The reqwest client
+// is reference-counted internally, so it's cheap to clone into each component.
+pub fn make_http_client(config: &LDConfig) -> Result<reqwest::Client, String> {
+	let http = &config.http;
+	let mut builder = reqwest::Client::builder()
+		.connect_timeout(Duration::from_millis(http.connect_timeout_millis))
+		.timeout(Duration::from_millis(http.request_timeout_millis));
+	if let Some(ref p) = http.proxy {
+		let mut proxy = reqwest::Proxy::all(p.url.as_str())
+			.map_err(|e| format!("invalid proxy URL \"{}\": {}", p.url, e))?;
+		if let Some(ref username) = p.username {
+			proxy = proxy.basic_auth(username, p.password.as_ref().map_or("", |s| s.as_str()));
+		}
+		builder = builder.proxy(proxy);
+	}
+	for pem in &http.root_certificates {
+		let cert = reqwest::Certificate::from_pem(pem)
+			.map_err(|e| format!("invalid root certificate: {}", e))?;
+		builder = builder.add_root_certificate(cert);
+	}
+	let mut headers = HeaderMap::new();
+	for (name, value) in &sdk_headers(config) {
+		add_header(&mut headers, name, value)?;
+	}
+	for (name, value) in &http.headers {
+		add_header(&mut headers, name, value)?;
+	}
+	builder = builder.default_headers(headers);
+	builder.build().map_err(|e| format!("unable to create HTTP client: {}", e))
+}
+
+// The headers that identify this SDK, and whatever is wrapping it, on every request. An invalid
+// application id or version is left out of the tags rather than stopping the client starting.
+fn sdk_headers(config: &LDConfig) -> Vec<(String, String)> {
+	let mut headers = vec![("User-Agent".to_owned(), USER_AGENT.to_owned())];
+	if let Some(ref name) = config.wrapper_name {
+		let wrapper = match config.wrapper_version {
+			Some(ref version) => format!("{}/{}", name, version),
+			None => name.clone()
+		};
+		headers.push(("X-LaunchDarkly-Wrapper".to_owned(), wrapper));
+	}
+	let mut tags: Vec<String> = vec![];
+	let tag_values = [("application-id", &config.application_id), ("application-version", &config.application_version)];
+	for (name, value) in &tag_values {
+		if let Some(ref value) = value {
+			if validate_tag_value(value).is_ok() {
+				tags.push(format!("{}/{}", name, value));
+			} else {
+				// TODO: error logging
+			}
+		}
+	}
+	if !tags.is_empty() {
+		headers.push(("X-LaunchDarkly-Tags".to_owned(), tags.join(" ")));
+	}
+	headers
+}
+
+// Tag values may only contain ASCII letters, digits, '.', '-' and '_', up to 64 characters.
+fn validate_tag_value(value: &str) -> Result<(), String> {
+	if value.is_empty() || value.len() > 64 {
+		return Err(format!("tag value \"{}\" must be between 1 and 64 characters long", value));
+	}
+	if !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_') {
+		return Err(format!("tag value \"{}\" may only contain letters, digits, '.', '-' and '_'", value));
+	}
+	Ok(())
+}
+
+fn add_header(headers: &mut HeaderMap, name: &str, value: &str) -> Result<(), String> {
+	let header_name = HeaderName::from_bytes(name.as_bytes())
+		.map_err(|_| format!("invalid header name \"{}\"", name))?;
+	let header_value = HeaderValue::from_str(value)
+		.map_err(|_| format!("invalid value for header \"{}\"", name))?;
+	headers.insert(header_name, header_value);
+	Ok(())
+}
+
 impl Requestor {
 
-	pub fn new(sdk_key: String, base_uri: &String) -> Requestor {
+	pub fn new(sdk_key: String, base_uri: &str, client: reqwest::Client) -> Requestor {
 		Requestor {
-			sdk_key: sdk_key,
-			base_uri: base_uri.clone(),
-			client: reqwest::Client::new()
+			sdk_key,
+			base_uri: base_uri.to_owned(),
+			client
 		}
 	}
 
 	pub fn get_all_flags(&self) -> Result<HashMap<String, FeatureFlag>, String> {
 		let uri = format!("{}/sdk/latest-all", self.base_uri);
 		let resp_result = self.client.get(&uri)
-			.header(reqwest::header::Authorization(self.sdk_key.clone()))
+			.header(reqwest::header::AUTHORIZATION, self.sdk_key.clone())
 			.send();
 		match resp_result {
 			Ok(mut resp) => match resp.text() {
@@ -4,25 +4,40 @@ use std::collections::HashMap;
 use serde_json::Value;
 
 
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, PartialEq)]
 pub struct LDUser {
 	pub key: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub secondary: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub ip: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub email: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub name: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub avatar: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub firstName: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub lastName: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub anonymous: Option<bool>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub country: Option<String>,
-	pub custom: Option<HashMap<String, Value>>
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub custom: Option<HashMap<String, Value>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub privateAttributeNames: Option<Vec<String>>
 }
 
 impl LDUser {
 
 	pub fn new(key: String) -> LDUser {
 		LDUser {
-			key: key,
+			key,
 			secondary: None,
 			ip: None,
 			email: None,
@@ -32,40 +47,21 @@ impl LDUser {
 			lastName: None,
 			anonymous: None,
 			country: None,
-			custom: None
+			custom: None,
+			privateAttributeNames: None
 		}
 	}
 
+	pub fn builder(key: String) -> LDUserBuilder {
+		LDUserBuilder { user: LDUser::new(key) }
+	}
+
 	pub fn with_email(&self, value: Option<String>) -> LDUser {
-		LDUser {
-			key: self.key.clone(),
-			secondary: self.secondary.clone(),
-			ip: self.ip.clone(),
-			email: value.clone(),
-			name: self.name.clone(),
-			avatar: self.avatar.clone(),
-			firstName: self.firstName.clone(),
-			lastName: self.lastName.clone(),
-			anonymous: self.anonymous.clone(),
-			country: self.country.clone(),
-			custom: self.custom.clone()
-		}
+		LDUser { email: value, ..self.clone() }
 	}
 
 	pub fn with_name(&self, value: Option<String>) -> LDUser {
-		LDUser {
-			key: self.key.clone(),
-			secondary: self.secondary.clone(),
-			ip: self.ip.clone(),
-			email: self.email.clone(),
-			name: value.clone(),
-			avatar: self.avatar.clone(),
-			firstName: self.firstName.clone(),
-			lastName: self.lastName.clone(),
-			anonymous: self.anonymous.clone(),
-			country: self.country.clone(),
-			custom: self.custom.clone()
-		}
+		LDUser { name: value, ..self.clone() }
 	}
 
 	pub fn get_value_for_evaluation(&self, attr: &String) -> Value {
@@ -90,3 +86,95 @@ impl LDUser {
 		}
 	}
 }
+
+// Builds an LDUser one attribute at a time, e.g.
+// LDUser::builder(key).email(email).custom_string("plan", "pro").private_attribute("email").build()
+pub struct LDUserBuilder {
+	user: LDUser
+}
+
+impl LDUserBuilder {
+
+	pub fn secondary(mut self, value: String) -> LDUserBuilder {
+		self.user.secondary = Some(value);
+		self
+	}
+
+	pub fn ip(mut self, value: String) -> LDUserBuilder {
+		self.user.ip = Some(value);
+		self
+	}
+
+	pub fn email(mut self, value: String) -> LDUserBuilder {
+		self.user.email = Some(value);
+		self
+	}
+
+	pub fn name(mut self, value: String) -> LDUserBuilder {
+		self.user.name = Some(value);
+		self
+	}
+
+	pub fn avatar(mut self, value: String) -> LDUserBuilder {
+		self.user.avatar = Some(value);
+		self
+	}
+
+	pub fn first_name(mut self, value: String) -> LDUserBuilder {
+		self.user.firstName = Some(value);
+		self
+	}
+
+	pub fn last_name(mut self, value: String) -> LDUserBuilder {
+		self.user.lastName = Some(value);
+		self
+	}
+
+	pub fn anonymous(mut self, value: bool) -> LDUserBuilder {
+		self.user.anonymous = Some(value);
+		self
+	}
+
+	pub fn country(mut self, value: String) -> LDUserBuilder {
+		self.user.country = Some(value);
+		self
+	}
+
+	pub fn custom(mut self, name: &str, value: Value) -> LDUserBuilder {
+		self.user.custom.get_or_insert_with(HashMap::new).insert(name.to_owned(), value);
+		self
+	}
+
+	pub fn custom_string(self, name: &str, value: &str) -> LDUserBuilder {
+		self.custom(name, Value::from(value))
+	}
+
+	// Non-finite numbers can't be represented in JSON, so they are stored as null.
+	pub fn custom_number(self, name: &str, value: f64) -> LDUserBuilder {
+		self.custom(name, Value::from(value))
+	}
+
+	pub fn custom_bool(self, name: &str, value: bool) -> LDUserBuilder {
+		self.custom(name, Value::from(value))
+	}
+
+	pub fn custom_list<T: Into<Value>>(self, name: &str, values: Vec<T>) -> LDUserBuilder {
+		self.custom(name, Value::Array(values.into_iter().map(|v| v.into()).collect()))
+	}
+
+	// Marks a built-in or custom attribute as private, so that it is never sent to LaunchDarkly
+	// in analytics events. It is still used for flag evaluation.
+	pub fn private_attribute(mut self, name: &str) -> LDUserBuilder {
+		{
+			let names = self.user.privateAttributeNames.get_or_insert_with(Vec::new);
+			if !names.iter().any(|n| n == name) {
+				names.push(name.to_owned());
+			}
+		}
+		self
+	}
+
+	pub fn build(self) -> LDUser {
+		self.user
+	}
+}
@@ -0,0 +1,155 @@
+
+use serde_json::{Map, Value};
+
+use attribute_ref::AttributeRef;
+use config::LDConfig;
+use context::LDContext;
+use user::LDUser;
+
+
+// Produces the JSON representation of a user that goes into analytics events, with private
+// attributes removed. The names of removed attributes are listed in "privateAttrs" so that
+// LaunchDarkly knows they existed. Evaluation always uses the unfiltered LDUser.
+//
+// A private attribute name may also be an attribute reference like "/address/street", which
+// removes just that property of a nested object and lists the reference as it was given.
+pub struct UserFilter {
+	all_attributes_private: bool,
+	// each name as given, and parsed, since they're checked for every context in every event
+	private_attributes: Vec<(String, AttributeRef)>
+}
+
+// These are the user attributes that appear at the top level of the user JSON; anything else
+// goes in "custom". The key and the anonymous flag are always sent, since events are useless
+// without them.
+const PRIVATABLE_BUILT_INS: [&str; 8] =
+	["secondary", "ip", "email", "name", "avatar", "firstName", "lastName", "country"];
+
+impl UserFilter {
+
+	pub fn new(config: &LDConfig) -> UserFilter {
+		UserFilter {
+			all_attributes_private: config.all_attributes_private,
+			private_attributes: parse_refs(&config.private_attribute_names)
+		}
+	}
+
+	pub fn filter_user(&self, user: &LDUser) -> Value {
+		self.filter_context(&LDContext::from(user))
+	}
+
+	// A context of kind "user" is written in the same format as an LDUser. Other kinds are
+	// written with their kind and attributes at the top level, and the names of the removed
+	// attributes in "_meta". A multi-kind context is an object with one such entry per kind.
+	pub fn filter_context(&self, context: &LDContext) -> Value {
+		if context.is_multi() {
+			let mut out = Map::new();
+			out.insert("kind".to_owned(), json!(context.kind()));
+			for c in context.individual_contexts() {
+				let mut single = self.filter_single_context(c);
+				single.remove("kind");
+				out.insert(c.kind().to_owned(), Value::Object(single));
+			}
+			Value::Object(out)
+		} else if context.kind() == "user" {
+			self.filter_user_context(context)
+		} else {
+			Value::Object(self.filter_single_context(context))
+		}
+	}
+
+	fn filter_user_context(&self, context: &LDContext) -> Value {
+		let mut out = Map::new();
+		let mut custom = Map::new();
+		let mut private_attrs: Vec<String> = vec![];
+		out.insert("key".to_owned(), json!(context.key()));
+		let context_refs = parse_refs(context.private_attributes());
+		for name in context.attribute_names() {
+			let value = context.get_value(name);
+			if name == "anonymous" {
+				out.insert(name.clone(), value);
+				continue;
+			}
+			match self.redact(&context_refs, name, value, &mut private_attrs) {
+				None => (),
+				Some(v) => if PRIVATABLE_BUILT_INS.contains(&name.as_str()) {
+					out.insert(name.clone(), v);
+				} else {
+					custom.insert(name.clone(), v);
+				}
+			}
+		}
+		if !custom.is_empty() {
+			out.insert("custom".to_owned(), Value::Object(custom));
+		}
+		if !private_attrs.is_empty() {
+			out.insert("privateAttrs".to_owned(), json!(private_attrs));
+		}
+		Value::Object(out)
+	}
+
+	fn filter_single_context(&self, context: &LDContext) -> Map<String, Value> {
+		let mut out = Map::new();
+		let mut redacted: Vec<String> = vec![];
+		out.insert("kind".to_owned(), json!(context.kind()));
+		out.insert("key".to_owned(), json!(context.key()));
+		let context_refs = parse_refs(context.private_attributes());
+		for name in context.attribute_names() {
+			let value = context.get_value(name);
+			if name == "anonymous" {
+				out.insert(name.clone(), value);
+			} else if let Some(v) = self.redact(&context_refs, name, value, &mut redacted) {
+				out.insert(name.clone(), v);
+			}
+		}
+		if !redacted.is_empty() {
+			out.insert("_meta".to_owned(), json!({ "redactedAttributes": redacted }));
+		}
+		out
+	}
+
+	// Returns the attribute's value without its private parts, or None if the whole attribute is
+	// private. The private attribute references that applied are added to `redacted`.
+	fn redact(&self, context_refs: &[(String, AttributeRef)], name: &str, value: Value,
+			  redacted: &mut Vec<String>) -> Option<Value> {
+		if self.all_attributes_private {
+			redacted.push(name.to_owned());
+			return None;
+		}
+		let refs: Vec<&(String, AttributeRef)> = self.private_attributes.iter().chain(context_refs)
+			.filter(|(_, attr_ref)| attr_ref.is_valid() && attr_ref.components()[0] == name)
+			.collect();
+		if let Some((text, _)) = refs.iter().find(|(_, attr_ref)| attr_ref.components().len() == 1) {
+			redacted.push(text.clone());
+			return None;
+		}
+		let mut value = value;
+		for (text, attr_ref) in refs {
+			if remove_path(&mut value, &attr_ref.components()[1..]) {
+				redacted.push(text.clone());
+			}
+		}
+		Some(value)
+	}
+}
+
+fn parse_refs(names: &[String]) -> Vec<(String, AttributeRef)> {
+	names.iter().map(|name| (name.clone(), AttributeRef::new(name))).collect()
+}
+
+// Removes the property at the end of a path of nested object keys, if there is one.
+fn remove_path(value: &mut Value, path: &[String]) -> bool {
+	match (value, path.split_last()) {
+		(&mut Value::Object(ref mut map), Some((last, parents))) => {
+			let mut current = map;
+			for key in parents {
+				current = match current.get_mut(key) {
+					Some(&mut Value::Object(ref mut child)) => child,
+					_ => return false
+				};
+			}
+			current.remove(last).is_some()
+		},
+		_ => false
+	}
+}
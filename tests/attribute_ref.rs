@@ -0,0 +1,87 @@
+// Tests for parsing attribute references and looking them up in contexts.
+
+extern crate ldclient;
+#[macro_use]
+extern crate serde_json;
+
+use serde_json::Value;
+
+use ldclient::attribute_ref::AttributeRef;
+use ldclient::context::LDContext;
+
+
+fn components(s: &str) -> Vec<String> {
+	let attr_ref = AttributeRef::new(s);
+	assert!(attr_ref.is_valid(), "{:?} should be valid", s);
+	attr_ref.components().clone()
+}
+
+#[test]
+fn path_is_split_into_components() {
+	assert_eq!(vec!["a"], components("/a"));
+	assert_eq!(vec!["a", "b", "c"], components("/a/b/c"));
+}
+
+#[test]
+fn tilde_escapes_are_unescaped() {
+	assert_eq!(vec!["a~b"], components("/a~0b"));
+	assert_eq!(vec!["a/b"], components("/a~1b"));
+	assert_eq!(vec!["~1", "/"], components("/~01/~1"));
+}
+
+#[test]
+fn invalid_refs() {
+	for s in &["", "/", "/a//b", "/a/", "/~2", "/a~", "/~"] {
+		let attr_ref = AttributeRef::new(s);
+		assert!(!attr_ref.is_valid(), "{:?} should be invalid", s);
+		assert!(!attr_ref.is_top_level(s), "{:?}", s);
+	}
+}
+
+#[test]
+fn name_without_leading_slash_is_literal() {
+	assert_eq!(vec!["a/b"], components("a/b"));
+	assert_eq!(vec!["~1"], components("~1"));
+	assert!(AttributeRef::new("a/b").is_top_level("a/b"));
+}
+
+#[test]
+fn is_top_level() {
+	assert!(AttributeRef::new("kind").is_top_level("kind"));
+	assert!(AttributeRef::new("/kind").is_top_level("kind"));
+	assert!(!AttributeRef::new("/kind/x").is_top_level("kind"));
+	assert!(!AttributeRef::new("name").is_top_level("kind"));
+}
+
+fn context() -> LDContext {
+	LDContext::builder("org", "org-key".to_owned())
+		.set("address", json!({ "street": { "line1": "1 Main St" }, "zip": "94612" }))
+		.set("a/b", json!("slash"))
+		.set("tags", json!(["x", "y"]))
+		.build().unwrap()
+}
+
+#[test]
+fn looks_up_nested_object_properties() {
+	let c = context();
+	assert_eq!(json!("94612"), c.get_value_for_ref(&AttributeRef::new("/address/zip")));
+	assert_eq!(json!("1 Main St"), c.get_value_for_ref(&AttributeRef::new("/address/street/line1")));
+	assert_eq!(json!({ "line1": "1 Main St" }), c.get_value_for_ref(&AttributeRef::new("/address/street")));
+	assert_eq!(json!("org-key"), c.get_value_for_ref(&AttributeRef::new("/key")));
+}
+
+#[test]
+fn missing_or_non_object_path_is_null() {
+	let c = context();
+	for s in &["/address/city", "/address/zip/x", "/tags/0", "/missing/x", "/address//zip", "address/zip"] {
+		assert_eq!(Value::Null, c.get_value_for_ref(&AttributeRef::new(s)), "{:?}", s);
+	}
+}
+
+#[test]
+fn literal_and_escaped_names_with_slash() {
+	let c = context();
+	assert_eq!(json!("slash"), c.get_value_for_ref(&AttributeRef::new("a/b")));
+	assert_eq!(json!("slash"), c.get_value_for_ref(&AttributeRef::new("/a~1b")));
+	assert_eq!(Value::Null, c.get_value_for_ref(&AttributeRef::new("/a/b")));
+}
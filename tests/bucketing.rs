@@ -0,0 +1,159 @@
+
+// Checks that rollouts put contexts in the same buckets as the other LaunchDarkly SDKs. The
+// expected bucket values are the reference vectors shared by the SDKs' own test suites; each test
+// builds a two-way rollout whose split point is just below or just above the expected bucket.
+
+extern crate ldclient;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use serde_json::Value;
+
+use ldclient::context::LDContext;
+use ldclient::evaluation::EvaluationReason;
+use ldclient::flag::FeatureFlag;
+use ldclient::user::LDUser;
+
+
+fn rollout_flag(rollout: Value) -> FeatureFlag {
+	common::flag(json!({
+		"key": "hashKey",
+		"salt": "saltyA",
+		"fallthrough": { "rollout": rollout },
+		"variations": ["first", "second", "third"]
+	}))
+}
+
+fn split_at(weight: u32) -> Value {
+	json!([
+		{ "variation": 0, "weight": weight },
+		{ "variation": 1, "weight": 100000 - weight }
+	])
+}
+
+fn evaluate(flag: &FeatureFlag, user: &LDUser) -> Value {
+	common::evaluate(flag, &LDContext::from(user)).value
+}
+
+// Asserts that the user's bucket, scaled to rollout weights, is in [weight, weight + 1).
+fn assert_bucket(rollout: Value, user: &LDUser, weight: u32) {
+	let mut below = rollout.clone();
+	below["variations"] = split_at(weight);
+	assert_eq!(json!("second"), evaluate(&rollout_flag(below), user), "bucket should be >= {}", weight);
+	let mut above = rollout;
+	above["variations"] = split_at(weight + 1);
+	assert_eq!(json!("first"), evaluate(&rollout_flag(above), user), "bucket should be < {}", weight + 1);
+}
+
+#[test]
+fn buckets_by_key_with_flag_key_and_salt() {
+	// 0.42157587, 0.6708485, 0.10343106
+	assert_bucket(json!({}), &LDUser::new("userKeyA".to_owned()), 42157);
+	assert_bucket(json!({}), &LDUser::new("userKeyB".to_owned()), 67084);
+	assert_bucket(json!({}), &LDUser::new("userKeyC".to_owned()), 10343);
+}
+
+#[test]
+fn seed_replaces_flag_key_and_salt() {
+	// 0.09801207, 0.14483777, 0.9242641
+	let rollout = json!({ "seed": 61 });
+	assert_bucket(rollout.clone(), &LDUser::new("userKeyA".to_owned()), 9801);
+	assert_bucket(rollout.clone(), &LDUser::new("userKeyB".to_owned()), 14483);
+	assert_bucket(rollout, &LDUser::new("userKeyC".to_owned()), 92426);
+}
+
+#[test]
+fn integer_and_string_attributes_bucket_the_same() {
+	// 0.54771423
+	let rollout = json!({ "bucketBy": "attr" });
+	let int_user = LDUser::builder("userKeyD".to_owned()).custom("attr", json!(33333)).build();
+	let float_int_user = LDUser::builder("userKeyD".to_owned()).custom("attr", json!(33333.0)).build();
+	let string_user = LDUser::builder("userKeyD".to_owned()).custom_string("attr", "33333").build();
+	assert_bucket(rollout.clone(), &int_user, 54771);
+	assert_bucket(rollout.clone(), &float_int_user, 54771);
+	assert_bucket(rollout, &string_user, 54771);
+}
+
+#[test]
+fn non_integer_and_missing_attributes_go_in_bucket_zero() {
+	let mut rollout = json!({ "bucketBy": "attr" });
+	rollout["variations"] = split_at(1);
+	let flag = rollout_flag(rollout);
+	let float_user = LDUser::builder("userKeyE".to_owned()).custom("attr", json!(999.999)).build();
+	let bool_user = LDUser::builder("userKeyE".to_owned()).custom_bool("attr", true).build();
+	let missing_user = LDUser::new("userKeyE".to_owned());
+	assert_eq!(json!("first"), evaluate(&flag, &float_user));
+	assert_eq!(json!("first"), evaluate(&flag, &bool_user));
+	assert_eq!(json!("first"), evaluate(&flag, &missing_user));
+}
+
+#[test]
+fn secondary_key_is_appended_to_the_hash_input() {
+	// sha1("hashKey.saltyA.userKeyA.mySecondary") gives 0.04304844
+	let user = LDUser::builder("userKeyA".to_owned()).secondary("mySecondary".to_owned()).build();
+	assert_bucket(json!({}), &user, 4304);
+}
+
+#[test]
+fn experiments_ignore_secondary_key_and_bucket_by() {
+	let user = LDUser::builder("userKeyA".to_owned())
+		.secondary("mySecondary".to_owned())
+		.custom_string("attr", "33333")
+		.build();
+	assert_bucket(json!({ "kind": "experiment", "seed": 61, "bucketBy": "attr" }), &user, 9801);
+}
+
+#[test]
+fn context_past_the_last_bucket_goes_in_the_last_variation() {
+	// userKeyB is in bucket 0.6708485, beyond the total weight of 60%
+	let flag = rollout_flag(json!({
+		"variations": [
+			{ "variation": 0, "weight": 30000 },
+			{ "variation": 2, "weight": 30000 }
+		]
+	}));
+	assert_eq!(json!("third"), evaluate(&flag, &LDUser::new("userKeyB".to_owned())));
+}
+
+#[test]
+fn missing_context_kind_goes_in_bucket_zero() {
+	let mut rollout = json!({ "contextKind": "org" });
+	rollout["variations"] = split_at(1);
+	let flag = rollout_flag(rollout);
+	assert_eq!(json!("first"), evaluate(&flag, &LDUser::new("userKeyB".to_owned())));
+}
+
+#[test]
+fn buckets_by_the_requested_context_kind() {
+	let org = LDContext::builder("org", "userKeyB".to_owned()).build().unwrap();
+	let user = LDContext::from(&LDUser::new("userKeyA".to_owned()));
+	let context = LDContext::multi(vec![user, org]).unwrap();
+	let mut rollout = json!({ "contextKind": "org" });
+	rollout["variations"] = split_at(67084);
+	let flag = rollout_flag(rollout);
+	assert_eq!(json!("second"), common::evaluate(&flag, &context).value);
+}
+
+#[test]
+fn experiment_reports_in_experiment_unless_untracked() {
+	let user = LDUser::new("userKeyA".to_owned());
+	let tracked = rollout_flag(json!({
+		"kind": "experiment",
+		"variations": [
+			{ "variation": 0, "weight": 100000 },
+			{ "variation": 1, "weight": 0 }
+		]
+	}));
+	let untracked = rollout_flag(json!({
+		"kind": "experiment",
+		"variations": [
+			{ "variation": 0, "weight": 100000, "untracked": true },
+			{ "variation": 1, "weight": 0 }
+		]
+	}));
+	let context = LDContext::from(&user);
+	assert_eq!(EvaluationReason::Fallthrough { in_experiment: true }, common::evaluate(&tracked, &context).reason);
+	assert_eq!(EvaluationReason::Fallthrough { in_experiment: false }, common::evaluate(&untracked, &context).reason);
+}
@@ -0,0 +1,52 @@
+// Flag helpers shared by the evaluation tests. Not every test file uses all of them.
+#![allow(dead_code)]
+
+use serde_json;
+use serde_json::Value;
+
+use ldclient::context::LDContext;
+use ldclient::evaluation::EvaluationDetail;
+use ldclient::feature_store::FeatureStore;
+use ldclient::flag::FeatureFlag;
+use ldclient::user::LDUser;
+
+
+// A flag that is on, with no targets or rules, and serves variation 0 of [false, true]. Any
+// properties in `overrides` replace these.
+pub fn flag(overrides: Value) -> FeatureFlag {
+	let mut json = json!({
+		"key": "flag",
+		"version": 1,
+		"on": true,
+		"prerequisites": [],
+		"salt": "salt",
+		"targets": [],
+		"rules": [],
+		"fallthrough": { "variation": 0 },
+		"offVariation": 0,
+		"variations": [false, true],
+		"deleted": false
+	});
+	for (name, value) in overrides.as_object().unwrap() {
+		json[name] = value.clone();
+	}
+	serde_json::from_value(json).unwrap()
+}
+
+// A flag that serves true if its one rule, with just the given clause, matches.
+pub fn clause_flag(clause: Value) -> FeatureFlag {
+	flag(json!({ "rules": [{ "clauses": [clause], "variation": 1 }] }))
+}
+
+// Evaluates the flag with no other flags available.
+pub fn evaluate(flag: &FeatureFlag, context: &LDContext) -> EvaluationDetail {
+	flag.evaluate(context, &FeatureStore::in_memory_store()).0
+}
+
+// Returns true if a user whose `attribute` is user_value matches a clause with the given operator
+// and value.
+pub fn clause_matches(attribute: &str, user_value: Value, op: &str, clause_value: Value) -> bool {
+	let flag = clause_flag(json!({ "attribute": attribute, "op": op, "values": [clause_value], "negate": false }));
+	let user = LDUser::builder("user".to_owned()).custom(attribute, user_value).build();
+	evaluate(&flag, &LDContext::from(&user)).value == json!(true)
+}
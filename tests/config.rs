@@ -0,0 +1,154 @@
+
+// Tests for loading LDConfig from environment variables and files, and in particular for the
+// error messages that malformed values produce.
+
+extern crate ldclient;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Mutex;
+
+use ldclient::client::LDClient;
+use ldclient::config::LDConfig;
+use ldclient::user::LDUser;
+
+
+// Environment variables are shared by every thread in the process, so tests that set them must
+// not run at the same time.
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+// Runs f with the given environment variables set, and removes them again afterwards.
+fn with_env<T>(vars: &[(&str, &str)], f: &dyn Fn() -> T) -> T {
+	let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+	for &(name, value) in vars {
+		env::set_var(name, value);
+	}
+	let result = f();
+	for &(name, _) in vars {
+		env::remove_var(name);
+	}
+	result
+}
+
+fn env_error(vars: &[(&str, &str)]) -> String {
+	match with_env(vars, &LDConfig::from_env) {
+		Ok(_) => panic!("expected an error for {:?}", vars),
+		Err(e) => e
+	}
+}
+
+// Writes a config file with a name unique to this test process, and returns its path.
+fn write_file(name: &str, content: &str) -> PathBuf {
+	let path = env::temp_dir().join(format!("ldclient-config-{}-{}", process::id(), name));
+	fs::write(&path, content).unwrap();
+	path
+}
+
+fn file_error(name: &str, content: &str) -> String {
+	let path = write_file(name, content);
+	let result = LDConfig::from_file(&path);
+	fs::remove_file(&path).unwrap();
+	match result {
+		Ok(_) => panic!("expected an error for {}", content),
+		Err(e) => e
+	}
+}
+
+#[test]
+fn from_env_reads_variables() {
+	let config = with_env(&[
+		("LD_SDK_KEY", "sdk-key"),
+		("LD_BASE_URI", "http://relay"),
+		("LD_OFFLINE", "yes"),
+		("LD_POLL_INTERVAL", " 60000 "),
+		("LD_EVENTS_CAPACITY", "500"),
+		("LD_PROXY_URL", "http://proxy:3128"),
+		("LD_PROXY_USERNAME", "me"),
+		("LD_PRIVATE_ATTRIBUTE_NAMES", "email, ip,,")
+	], &LDConfig::from_env).unwrap();
+	assert_eq!(Some("sdk-key".to_owned()), config.sdk_key);
+	assert_eq!("http://relay", config.base_uri);
+	assert!(config.offline);
+	assert_eq!(60000, config.polling_interval_millis);
+	assert_eq!(500, config.events_capacity);
+	let proxy = config.http.proxy.unwrap();
+	assert_eq!("http://proxy:3128", proxy.url);
+	assert_eq!(Some("me".to_owned()), proxy.username);
+	assert_eq!(Some("".to_owned()), proxy.password);
+	assert_eq!(vec!["email".to_owned(), "ip".to_owned()], config.private_attribute_names);
+}
+
+#[test]
+fn from_env_leaves_unset_variables_at_their_defaults() {
+	let config = with_env(&[], &LDConfig::from_env).unwrap();
+	let defaults = LDConfig::default();
+	assert_eq!(None, config.sdk_key);
+	assert_eq!(defaults.base_uri, config.base_uri);
+	assert_eq!(defaults.polling_interval_millis, config.polling_interval_millis);
+}
+
+#[test]
+fn from_env_rejects_malformed_values() {
+	assert_eq!("LD_OFFLINE must be true or false, got \"maybe\"",
+		env_error(&[("LD_OFFLINE", "maybe")]));
+	assert_eq!("LD_POLL_INTERVAL must be a whole number of milliseconds, got \"30s\"",
+		env_error(&[("LD_POLL_INTERVAL", "30s")]));
+	assert_eq!("LD_START_WAIT must be a whole number of milliseconds, got \"-1\"",
+		env_error(&[("LD_START_WAIT", "-1")]));
+	assert_eq!("LD_EVENTS_CAPACITY must be a whole number, got \"1.5\"",
+		env_error(&[("LD_EVENTS_CAPACITY", "1.5")]));
+	assert_eq!("proxy credentials were given without a proxy URL",
+		env_error(&[("LD_PROXY_USERNAME", "me")]));
+}
+
+#[test]
+fn from_file_reads_toml_and_json() {
+	let toml_path = write_file("valid.toml", "sdk_key = \"sdk-key\"\nevents_capacity = 500\nproxy_url = \"http://proxy\"\n");
+	let json_path = write_file("valid.json", "{ \"sdk_key\": \"sdk-key\", \"events_capacity\": 500, \"proxy_url\": \"http://proxy\" }");
+	for path in &[toml_path, json_path] {
+		let config = LDConfig::from_file(path).unwrap();
+		fs::remove_file(path).unwrap();
+		assert_eq!(Some("sdk-key".to_owned()), config.sdk_key);
+		assert_eq!(500, config.events_capacity);
+		assert_eq!("http://proxy", config.http.proxy.unwrap().url);
+		assert_eq!(LDConfig::default().base_uri, config.base_uri);
+	}
+}
+
+#[test]
+fn from_file_rejects_malformed_files() {
+	let missing = env::temp_dir().join("ldclient-config-no-such-file.toml");
+	let error = LDConfig::from_file(&missing).err().unwrap();
+	assert!(error.starts_with(&format!("unable to read config file {}: ", missing.display())), "{}", error);
+
+	let error = file_error("syntax.toml", "sdk_key = ");
+	assert!(error.starts_with("invalid TOML in config file "), "{}", error);
+	let error = file_error("syntax.json", "{ \"sdk_key\": ");
+	assert!(error.starts_with("invalid JSON in config file "), "{}", error);
+
+	let error = file_error("type.toml", "events_capacity = \"lots\"");
+	assert!(error.starts_with("invalid TOML in config file ") && error.contains("invalid type"), "{}", error);
+
+	assert_eq!("proxy credentials were given without a proxy URL",
+		file_error("proxy.toml", "proxy_username = \"me\""));
+}
+
+#[test]
+fn from_file_rejects_unknown_settings() {
+	let error = file_error("unknown.toml", "sdk_kye = \"sdk-key\"");
+	assert!(error.contains("unknown field `sdk_kye`"), "{}", error);
+	let error = file_error("unknown.json", "{ \"pollInterval\": 1000 }");
+	assert!(error.contains("unknown field `pollInterval`"), "{}", error);
+}
+
+#[test]
+fn client_uses_configured_sdk_key() {
+	let config = LDConfig::default().with_offline(true);
+	assert_eq!("the configuration has no SDK key", LDClient::from_config(config.clone()).err().unwrap());
+	// the secure mode hash of this user with the SDK key "secret" is the shared test vector
+	let client = LDClient::from_config(config.with_sdk_key("secret".to_owned())).unwrap();
+	assert_eq!("aa747c502a898200f9e4fa21bac68136f886a0e27aec70ba06daf2e2a5cb5597",
+		client.secure_mode_hash(&LDUser::new("Message".to_owned())));
+}
@@ -0,0 +1,156 @@
+// Tests for building contexts, converting users to contexts, and targeting contexts by kind.
+
+extern crate ldclient;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use serde_json::Value;
+
+use ldclient::context::LDContext;
+use ldclient::user::LDUser;
+
+
+fn org() -> LDContext {
+	LDContext::builder("org", "org-key".to_owned()).name("Org".to_owned()).build().unwrap()
+}
+
+fn device() -> LDContext {
+	LDContext::builder("device", "device-key".to_owned()).set("os", json!("linux")).build().unwrap()
+}
+
+#[test]
+fn user_converts_to_user_context() {
+	let user = LDUser::builder("user-key".to_owned())
+		.email("test@example.com".to_owned())
+		.anonymous(true)
+		.custom_string("team", "a")
+		.private_attribute("email")
+		.build();
+	let context = LDContext::from(&user);
+	assert_eq!("user", context.kind());
+	assert_eq!("user-key", context.key());
+	assert_eq!(json!("test@example.com"), context.get_value("email"));
+	assert_eq!(json!(true), context.get_value("anonymous"));
+	assert_eq!(json!("a"), context.get_value("team"));
+	assert_eq!(Value::Null, context.get_value("name"));
+	assert_eq!(vec!["anonymous", "email", "team"], context.attribute_names());
+	assert_eq!(&vec!["email".to_owned()], context.private_attributes());
+}
+
+#[test]
+fn built_in_user_attribute_wins_over_custom_attribute_with_same_name() {
+	let user = LDUser::builder("user-key".to_owned())
+		.email("built-in@example.com".to_owned())
+		.custom_string("email", "custom@example.com")
+		.build();
+	assert_eq!(json!("built-in@example.com"), LDContext::from(&user).get_value("email"));
+}
+
+#[test]
+fn custom_attribute_named_like_unset_built_in_is_dropped() {
+	let user = LDUser::builder("user-key".to_owned())
+		.custom_string("name", "custom name")
+		.custom_string("key", "other-key")
+		.custom_string("kind", "org")
+		.build();
+	let context = LDContext::from(&user);
+	assert_eq!(Value::Null, context.get_value("name"));
+	assert_eq!("user-key", context.key());
+	assert_eq!("user", context.kind());
+	assert!(context.attribute_names().is_empty());
+}
+
+#[test]
+fn builder_ignores_kind_and_key_attributes() {
+	let context = LDContext::builder("org", "org-key".to_owned())
+		.set("kind", json!("user"))
+		.set("key", json!("other"))
+		.build().unwrap();
+	assert_eq!(json!("org"), context.get_value("kind"));
+	assert_eq!(json!("org-key"), context.get_value("key"));
+	assert!(context.attribute_names().is_empty());
+}
+
+#[test]
+fn builder_rejects_invalid_kinds_and_empty_key() {
+	for kind in &["", "kind", "multi", "a b", "org!"] {
+		assert!(LDContext::builder(kind, "key".to_owned()).build().is_err(), "kind {:?}", kind);
+	}
+	assert!(LDContext::builder("org", "".to_owned()).build().is_err());
+	assert!(LDContext::builder("my_org.v-2", "key".to_owned()).build().is_ok());
+}
+
+#[test]
+fn multi_rejects_empty_nested_and_duplicate_kinds() {
+	assert!(LDContext::multi(vec![]).is_err());
+	assert!(LDContext::multi(vec![org(), org()]).is_err());
+	let multi = LDContext::multi(vec![org(), device()]).unwrap();
+	assert!(LDContext::multi(vec![multi, LDContext::from(&LDUser::new("u".to_owned()))]).is_err());
+}
+
+#[test]
+fn multi_of_one_context_is_that_context() {
+	assert_eq!(org(), LDContext::multi(vec![org()]).unwrap());
+}
+
+#[test]
+fn kinds_and_as_kind() {
+	let multi = LDContext::multi(vec![org(), device()]).unwrap();
+	assert!(multi.is_multi());
+	assert_eq!("multi", multi.kind());
+	assert_eq!(vec!["device", "org"], multi.kinds());
+	assert_eq!(Some(&org()), multi.as_kind("org"));
+	assert_eq!(None, multi.as_kind("user"));
+	assert_eq!(vec!["org"], org().kinds());
+	assert_eq!(Some(&org()), org().as_kind("org"));
+	assert_eq!(None, org().as_kind("device"));
+}
+
+#[test]
+fn canonical_keys() {
+	assert_eq!("user-key", LDContext::from(&LDUser::new("user-key".to_owned())).canonical_key());
+	assert_eq!("org:org-key", org().canonical_key());
+	let escaped = LDContext::builder("org", "a:b%c".to_owned()).build().unwrap();
+	assert_eq!("org:a%3Ab%25c", escaped.canonical_key());
+	let multi = LDContext::multi(vec![org(), device()]).unwrap();
+	assert_eq!("device:device-key:org:org-key", multi.canonical_key());
+}
+
+// Returns true if the context matches a flag rule with the single given clause.
+fn clause_matches(clause: Value, context: &LDContext) -> bool {
+	common::evaluate(&common::clause_flag(clause), context).value == json!(true)
+}
+
+#[test]
+fn clause_matches_attribute_of_its_context_kind() {
+	let clause = json!({ "contextKind": "org", "attribute": "name", "op": "in", "values": ["Org"], "negate": false });
+	assert!(clause_matches(clause.clone(), &org()));
+	assert!(clause_matches(clause.clone(), &LDContext::multi(vec![org(), device()]).unwrap()));
+	assert!(!clause_matches(clause, &device()));
+}
+
+#[test]
+fn clause_without_context_kind_targets_user() {
+	let clause = json!({ "attribute": "name", "op": "in", "values": ["Org"], "negate": false });
+	assert!(!clause_matches(clause.clone(), &org()));
+	let user = LDContext::from(&LDUser::builder("u".to_owned()).name("Org".to_owned()).build());
+	assert!(clause_matches(clause.clone(), &user));
+	assert!(clause_matches(clause, &LDContext::multi(vec![org(), user]).unwrap()));
+}
+
+#[test]
+fn negated_clause_does_not_match_missing_context_kind() {
+	let clause = json!({ "contextKind": "org", "attribute": "name", "op": "in", "values": ["Other"], "negate": true });
+	assert!(clause_matches(clause.clone(), &org()));
+	assert!(!clause_matches(clause, &device()));
+}
+
+#[test]
+fn kind_clause_matches_any_kind_in_context() {
+	let clause = json!({ "contextKind": "user", "attribute": "kind", "op": "in", "values": ["org"], "negate": false });
+	assert!(clause_matches(clause.clone(), &org()));
+	assert!(clause_matches(clause.clone(), &LDContext::multi(vec![org(), device()]).unwrap()));
+	assert!(!clause_matches(clause, &device()));
+}
@@ -0,0 +1,170 @@
+
+// Table-driven tests for the before and after clause operators, which should accept the same
+// dates as the other LaunchDarkly SDKs: RFC 3339 timestamps, or milliseconds since the epoch.
+
+extern crate ldclient;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use serde_json::Value;
+
+
+// Returns true if a user whose "date" attribute is user_value matches a clause with the given
+// operator and value.
+fn clause_matches(user_value: Value, op: &str, clause_value: Value) -> bool {
+	common::clause_matches("date", user_value, op, clause_value)
+}
+
+fn check(cases: &[(Value, &str, Value, bool)]) {
+	for &(ref user_value, op, ref clause_value, expected) in cases {
+		assert_eq!(expected, clause_matches(user_value.clone(), op, clause_value.clone()),
+			"{} {} {}", user_value, op, clause_value);
+	}
+}
+
+#[test]
+fn epoch_millis() {
+	check(&[
+		(json!(0), "before", json!(1), true),
+		(json!(1), "before", json!(0), false),
+		(json!(1), "before", json!(1), false),
+		(json!(1), "after", json!(0), true),
+		(json!(0), "after", json!(1), false),
+		(json!(-1), "before", json!(0), true),
+		(json!(1772359200000i64), "after", json!(1772359199999i64), true)
+	]);
+}
+
+#[test]
+fn fractional_epoch_millis() {
+	check(&[
+		(json!(1.5), "after", json!(1), true),
+		(json!(1.5), "before", json!(2), true),
+		(json!(1.0), "before", json!(1), false),
+		(json!(1.0), "after", json!(1), false),
+		(json!(0.5), "after", json!(0.25), true),
+		(json!(-0.5), "before", json!(0), true),
+		(json!(1772359200000.0), "after", json!("2026-03-01T09:59:59.999Z"), true),
+		// too far from the epoch for any time, so never matches rather than panicking
+		(json!(1.2630441768696799e30), "after", json!(0), false),
+		(json!(0), "before", json!(1.2630441768696799e30), false),
+		(json!(-1.2630441768696799e30), "before", json!(0), false),
+		(json!(1e300), "after", json!(0), false)
+	]);
+}
+
+#[test]
+fn rfc3339_timestamps() {
+	check(&[
+		(json!("1970-01-01T00:00:00Z"), "before", json!("1970-01-01T00:00:01Z"), true),
+		(json!("1970-01-01T00:00:01Z"), "after", json!("1970-01-01T00:00:00Z"), true),
+		(json!("1970-01-01T00:00:00Z"), "after", json!("1970-01-01T00:00:00Z"), false),
+		(json!("2000-02-29T00:00:00Z"), "after", json!("2000-02-28T23:59:59Z"), true),
+		(json!("2025-12-31T23:59:59Z"), "before", json!("2026-01-01T00:00:00Z"), true),
+		// RFC 3339 allows lowercase "t" and "z"
+		(json!("2026-03-01t10:00:00z"), "after", json!("2026-03-01T09:59:59Z"), true)
+	]);
+}
+
+#[test]
+fn timestamps_can_be_compared_with_epoch_millis() {
+	check(&[
+		(json!("1970-01-01T00:00:00Z"), "before", json!(1), true),
+		(json!(1), "after", json!("1970-01-01T00:00:00Z"), true),
+		(json!("2026-03-01T10:00:00Z"), "before", json!(1772359200001i64), true),
+		(json!("2026-03-01T10:00:00Z"), "after", json!(1772359199999i64), true),
+		(json!("1969-12-31T23:59:59Z"), "before", json!(0), true)
+	]);
+}
+
+#[test]
+fn fractional_seconds() {
+	check(&[
+		(json!("1970-01-01T00:00:00.5Z"), "after", json!(499), true),
+		(json!("1970-01-01T00:00:00.5Z"), "before", json!(501), true),
+		(json!("1970-01-01T00:00:00.500Z"), "after", json!("1970-01-01T00:00:00.499999999Z"), true),
+		(json!("1970-01-01T00:00:00.123456789Z"), "before", json!("1970-01-01T00:00:00.12345679Z"), true),
+		// digits beyond nanoseconds are dropped
+		(json!("1970-01-01T00:00:00.0000000009Z"), "after", json!("1970-01-01T00:00:00Z"), false)
+	]);
+}
+
+#[test]
+fn time_zone_offsets() {
+	check(&[
+		// the example from the issue: 08:00:00.123 UTC
+		(json!("2026-03-01T10:00:00.123+02:00"), "after", json!("2026-03-01T08:00:00.122Z"), true),
+		(json!("2026-03-01T10:00:00.123+02:00"), "before", json!("2026-03-01T08:00:00.124Z"), true),
+		(json!("2026-03-01T10:00:00.123+02:00"), "after", json!(1772352000122i64), true),
+		(json!("1970-01-01T01:00:00+01:00"), "before", json!(1), true),
+		(json!("1969-12-31T19:00:00-05:00"), "after", json!(-1), true),
+		(json!("2026-03-01T00:30:00+05:30"), "before", json!("2026-02-28T19:00:01Z"), true),
+		(json!("2026-03-01T00:30:00+05:30"), "after", json!("2026-02-28T18:59:59Z"), true),
+		(json!("2026-03-01T10:00:00+00:00"), "after", json!("2026-03-01T10:00:00Z"), false),
+		(json!("2026-03-01T10:00:00-00:00"), "before", json!("2026-03-01T10:00:00Z"), false)
+	]);
+}
+
+#[test]
+fn negative_offsets_crossing_a_day_boundary() {
+	check(&[
+		// 2026-03-01T03:00:00Z, the next day in UTC
+		(json!("2026-02-28T22:00:00-05:00"), "after", json!("2026-03-01T02:59:59Z"), true),
+		(json!("2026-02-28T22:00:00-05:00"), "before", json!("2026-03-01T03:00:01Z"), true),
+		(json!("2026-02-28T22:00:00-05:00"), "after", json!(1772333999999i64), true),
+		(json!("2026-02-28T22:00:00-05:00"), "before", json!(1772334000001i64), true),
+		// and the next year
+		(json!("2025-12-31T23:30:00-01:00"), "after", json!("2026-01-01T00:29:59Z"), true),
+		(json!("2025-12-31T23:30:00-01:00"), "before", json!("2026-01-01T00:30:01Z"), true),
+		(json!("2026-01-01T00:30:00Z"), "after", json!("2025-12-31T23:29:59-01:00"), true)
+	]);
+}
+
+#[test]
+fn leap_day() {
+	check(&[
+		(json!("2024-02-29T12:00:00Z"), "after", json!(1709207999999i64), true),
+		(json!("2024-02-29T12:00:00Z"), "before", json!(1709208000001i64), true),
+		(json!("2024-02-29T00:00:00Z"), "after", json!("2024-02-28T23:59:59Z"), true),
+		(json!("2024-02-29T23:59:59Z"), "before", json!("2024-03-01T00:00:00Z"), true),
+		// 1 AM UTC on the leap day, then 1 AM UTC the day after it
+		(json!("2024-02-28T23:00:00-02:00"), "after", json!("2024-02-29T00:59:59Z"), true),
+		(json!("2024-02-29T22:00:00-03:00"), "before", json!(1709254800001i64), true),
+		(json!("2024-02-29T22:00:00-03:00"), "after", json!(1709254799999i64), true),
+		// 2000 is a leap year, because it's divisible by 400
+		(json!("2000-02-29T00:00:00Z"), "before", json!("2000-03-01T00:00:00Z"), true)
+	]);
+}
+
+#[test]
+fn feb_29_outside_leap_years_never_matches() {
+	for &date in &["2023-02-29T00:00:00Z", "1900-02-29T00:00:00Z", "2100-02-29T00:00:00Z"] {
+		for &op in &["before", "after"] {
+			assert!(!clause_matches(json!(date), op, json!(0)), "{:?} {}", date, op);
+			assert!(!clause_matches(json!(date), op, json!(4102444800000i64)), "{:?} {}", date, op);
+		}
+	}
+}
+
+#[test]
+fn invalid_dates_never_match() {
+	let invalid = [
+		"", "x", "2026-03-01", "2026-03-01T10:00:00", "2026-03-01 10:00:00Z", "2026-3-01T10:00:00Z",
+		"2026-03-01T10:00Z", "2026-03-01T10:00:00.Z", "2026-03-01T10:00:00+0200", "2026-03-01T10:00:00+02",
+		"2026-03-01T10:00:00Zjunk", "2026-13-01T10:00:00Z", "2026-02-29T10:00:00Z", "2026-04-31T10:00:00Z",
+		"2026-03-01T24:00:00Z", "2026-03-01T10:60:00Z", "2026-03-01T10:00:60Z", "2026-03-01T10:00:00+24:00",
+		"2026-03-01T10:00:0éZ"
+	];
+	for &op in &["before", "after"] {
+		for &date in &invalid {
+			assert!(!clause_matches(json!(date), op, json!(0)), "{:?} {}", date, op);
+			assert!(!clause_matches(json!(date), op, json!(4102444800000i64)), "{:?} {}", date, op);
+			assert!(!clause_matches(json!(0), op, json!(date)), "{} {:?}", op, date);
+			assert!(!clause_matches(json!(4102444800000i64), op, json!(date)), "{} {:?}", op, date);
+		}
+		assert!(!clause_matches(json!(true), op, json!(0)), "bool {}", op);
+		assert!(!clause_matches(json!(null), op, json!(0)), "null {}", op);
+	}
+}
@@ -0,0 +1,95 @@
+// Tests for the contents of diagnostic events.
+
+extern crate ldclient;
+#[macro_use]
+extern crate serde_json;
+
+use serde_json::Value;
+
+use ldclient::config::{HttpConfig, LDConfig};
+use ldclient::diagnostics;
+use ldclient::diagnostics::{DiagnosticId, DiagnosticStats, DiagnosticsRecorder};
+
+
+const SDK_KEY: &str = "sdk-0123456789-abcdef";
+
+#[test]
+fn init_event_leaves_out_sdk_key_and_proxy_credentials() {
+	let http = HttpConfig::default()
+		.with_proxy("http://proxy.example.com:8080".to_owned())
+		.with_proxy_auth("proxy-user".to_owned(), "proxy-password".to_owned())
+		.unwrap();
+	let config = LDConfig::default().with_http(http);
+	let event = diagnostics::make_init_event(&DiagnosticId::new(SDK_KEY), &config);
+	let text = event.to_string();
+	for secret in &[SDK_KEY, "proxy-user", "proxy-password", "proxy.example.com"] {
+		assert!(!text.contains(secret), "{} in {}", secret, text);
+	}
+	assert_eq!(json!("abcdef"), event["id"]["sdkKeySuffix"]);
+	assert_eq!(json!(true), event["configuration"]["usingProxy"]);
+	assert_eq!(json!(true), event["configuration"]["usingProxyAuthenticator"]);
+}
+
+#[test]
+fn init_event_describes_default_config() {
+	let event = diagnostics::make_init_event(&DiagnosticId::new(SDK_KEY), &LDConfig::default());
+	assert_eq!(json!("diagnostic-init"), event["kind"]);
+	let configuration = &event["configuration"];
+	assert_eq!(json!(false), configuration["customBaseURI"]);
+	assert_eq!(json!(false), configuration["customEventsURI"]);
+	assert_eq!(json!(false), configuration["usingProxy"]);
+	assert_eq!(json!(false), configuration["usingProxyAuthenticator"]);
+	assert_eq!(Value::Null, event["sdk"]["wrapperName"]);
+}
+
+#[test]
+fn short_sdk_key_is_its_own_suffix() {
+	let event = diagnostics::make_init_event(&DiagnosticId::new("abc"), &LDConfig::default());
+	assert_eq!(json!("abc"), event["id"]["sdkKeySuffix"]);
+}
+
+#[test]
+fn periodic_event_reports_counters_and_stream_inits() {
+	let id = DiagnosticId::new(SDK_KEY);
+	let recorder = DiagnosticsRecorder::new();
+	recorder.record_stream_init(1000, 50, true);
+	recorder.record_stream_init(2000, 30, false);
+	let stats = DiagnosticStats {
+		data_since: 500,
+		dropped_events: 3,
+		deduplicated_users: 4,
+		events_in_last_batch: 5
+	};
+	let event = diagnostics::make_periodic_event(&id, &stats, &recorder);
+	assert_eq!(json!("diagnostic"), event["kind"]);
+	assert_eq!(json!(500), event["dataSinceDate"]);
+	assert_eq!(json!(3), event["droppedEvents"]);
+	assert_eq!(json!(4), event["deduplicatedUsers"]);
+	assert_eq!(json!(5), event["eventsInLastBatch"]);
+	assert_eq!(json!([
+		{ "timestamp": 1000, "durationMillis": 50, "failed": true },
+		{ "timestamp": 2000, "durationMillis": 30, "failed": false }
+	]), event["streamInits"]);
+	// the same diagnostic id is used for the whole life of the SDK
+	let init_event = diagnostics::make_init_event(&id, &LDConfig::default());
+	assert_eq!(init_event["id"], event["id"]);
+
+	// each stream init is only reported once
+	let next = diagnostics::make_periodic_event(&id, &DiagnosticStats::new(), &recorder);
+	assert_eq!(json!([]), next["streamInits"]);
+	assert_eq!(json!(0), next["droppedEvents"]);
+}
+
+#[test]
+fn stream_inits_are_capped_when_not_reported() {
+	let recorder = DiagnosticsRecorder::new();
+	for i in 0..1000 {
+		recorder.record_stream_init(i, 1, true);
+	}
+	let event = diagnostics::make_periodic_event(&DiagnosticId::new(SDK_KEY), &DiagnosticStats::new(), &recorder);
+	let inits = event["streamInits"].as_array().unwrap();
+	assert_eq!(100, inits.len());
+	// the most recent are kept
+	assert_eq!(json!(900), inits[0]["timestamp"]);
+	assert_eq!(json!(999), inits[99]["timestamp"]);
+}
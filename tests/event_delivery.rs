@@ -0,0 +1,137 @@
+// Tests for delivering analytics events to LaunchDarkly, against a local HTTP server.
+
+extern crate flate2;
+extern crate ldclient;
+extern crate reqwest;
+#[macro_use]
+extern crate serde_json;
+extern crate tiny_http;
+
+mod test_server;
+
+use std::sync::Arc;
+
+use ldclient::config::LDConfig;
+use ldclient::context::LDContext;
+use ldclient::diagnostics::DiagnosticsRecorder;
+use ldclient::event;
+use ldclient::event_processor::{EventProcessor, EventSendError, EventSender};
+use ldclient::user::LDUser;
+
+use test_server::TestServer;
+
+
+fn sender(server: &TestServer) -> EventSender {
+	EventSender::new("sdk-key".to_owned(), &server.uri, reqwest::Client::new())
+}
+
+#[test]
+fn events_are_posted_gzipped() {
+	let server = TestServer::with_statuses(vec![]);
+	let events = json!([{ "kind": "custom", "key": "a" }]);
+	assert!(sender(&server).send_events(&events).is_ok());
+	let request = server.next_request().unwrap();
+	assert_eq!("POST", request.method);
+	assert_eq!("/bulk", request.path);
+	assert_eq!(Some("sdk-key"), request.header("Authorization"));
+	assert_eq!(Some("application/json"), request.header("Content-Type"));
+	assert_eq!(Some("gzip"), request.header("Content-Encoding"));
+	assert_eq!(events, request.json());
+}
+
+#[test]
+fn diagnostic_events_go_to_their_own_path() {
+	let server = TestServer::with_statuses(vec![]);
+	let event = json!({ "kind": "diagnostic" });
+	assert!(sender(&server).send_diagnostic_event(&event).is_ok());
+	let request = server.next_request().unwrap();
+	assert_eq!("/diagnostic", request.path);
+	assert_eq!(event, request.json());
+}
+
+#[test]
+fn server_error_is_retried_once_with_same_payload_id() {
+	let server = TestServer::with_statuses(vec![503]);
+	let events = json!([{ "kind": "custom", "key": "a" }]);
+	assert!(sender(&server).send_events(&events).is_ok());
+	let first = server.next_request().unwrap();
+	let second = server.next_request().unwrap();
+	assert!(first.header("X-LaunchDarkly-Payload-ID").is_some());
+	assert_eq!(first.header("X-LaunchDarkly-Payload-ID"), second.header("X-LaunchDarkly-Payload-ID"));
+	assert_eq!(events, second.json());
+}
+
+#[test]
+fn payload_ids_differ_between_payloads() {
+	let server = TestServer::with_statuses(vec![]);
+	let s = sender(&server);
+	assert!(s.send_events(&json!([])).is_ok());
+	assert!(s.send_events(&json!([])).is_ok());
+	let first = server.next_request().unwrap();
+	let second = server.next_request().unwrap();
+	assert_ne!(first.header("X-LaunchDarkly-Payload-ID"), second.header("X-LaunchDarkly-Payload-ID"));
+}
+
+#[test]
+fn gives_up_after_second_server_error() {
+	let server = TestServer::with_statuses(vec![503, 500]);
+	assert!(matches!(sender(&server).send_events(&json!([])), Err(EventSendError::Failed(_))));
+	assert!(server.next_request().is_some());
+	assert!(server.next_request().is_some());
+	assert_eq!(0, server.received().len());
+}
+
+#[test]
+fn client_error_is_not_retried() {
+	let server = TestServer::with_statuses(vec![400]);
+	assert!(matches!(sender(&server).send_events(&json!([])), Err(EventSendError::Failed(_))));
+	assert!(server.next_request().is_some());
+	assert_eq!(0, server.received().len());
+}
+
+#[test]
+fn unauthorized_is_reported_without_retrying() {
+	for &status in &[401, 403] {
+		let server = TestServer::with_statuses(vec![status]);
+		assert!(matches!(sender(&server).send_events(&json!([])), Err(EventSendError::Unauthorized)), "{}", status);
+		assert!(server.next_request().is_some());
+		assert_eq!(0, server.received().len());
+	}
+}
+
+fn event_processor(server: &TestServer) -> EventProcessor {
+	let config = LDConfig::default()
+		.with_events_uri(server.uri.clone())
+		.with_diagnostic_opt_out(true);
+	EventProcessor::new("sdk-key".to_owned(), &config, reqwest::Client::new(), &Arc::new(DiagnosticsRecorder::new()))
+}
+
+#[test]
+fn event_processor_stops_sending_after_unauthorized() {
+	for &status in &[401, 403] {
+		let server = TestServer::with_statuses(vec![status]);
+		let processor = event_processor(&server);
+		let context = LDContext::from(&LDUser::new("user-key".to_owned()));
+		processor.send(event::custom("first", &context, None, None));
+		processor.flush();
+		processor.send(event::custom("second", &context, None, None));
+		processor.close();
+		assert!(server.next_request().is_some());
+		assert_eq!(0, server.received().len(), "{}", status);
+	}
+}
+
+#[test]
+fn event_processor_keeps_sending_after_other_errors() {
+	let server = TestServer::with_statuses(vec![400]);
+	let processor = event_processor(&server);
+	let context = LDContext::from(&LDUser::new("user-key".to_owned()));
+	processor.send(event::custom("first", &context, None, None));
+	processor.flush();
+	processor.send(event::custom("second", &context, None, None));
+	processor.close();
+	assert!(server.next_request().is_some());
+	let second = server.next_request().unwrap();
+	let events = second.json();
+	assert_eq!(json!("second"), events[0]["key"]);
+}
@@ -0,0 +1,298 @@
+// Tests for the analytics events that the client sends for flag evaluations, using a local HTTP
+// server for both the flag data and the events.
+
+extern crate flate2;
+extern crate ldclient;
+#[macro_use]
+extern crate serde_json;
+extern crate tiny_http;
+
+mod test_server;
+
+use std::thread;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use ldclient::client::LDClient;
+use ldclient::config::LDConfig;
+use ldclient::user::LDUser;
+
+use test_server::TestServer;
+
+
+fn flag(key: &str, prerequisites: Value) -> Value {
+	json!({
+		"key": key,
+		"version": 1,
+		"on": true,
+		"prerequisites": prerequisites,
+		"salt": "salt",
+		"targets": [],
+		"rules": [],
+		"fallthrough": { "variation": 1 },
+		"offVariation": 0,
+		"variations": ["off", "on"],
+		"trackEvents": true,
+		"deleted": false
+	})
+}
+
+fn start_server() -> TestServer {
+	start_server_with(json!({
+		"parent": flag("parent", json!([{ "key": "prereq", "variation": 1 }])),
+		"prereq": flag("prereq", json!([]))
+	}))
+}
+
+fn start_server_with(flags: Value) -> TestServer {
+	let flags = json!({ "flags": flags });
+	TestServer::start(move |request| {
+		if request.path == "/sdk/latest-all" {
+			(200, flags.to_string())
+		} else {
+			(202, String::new())
+		}
+	})
+}
+
+fn config(server: &TestServer) -> LDConfig {
+	LDConfig::default()
+		.with_base_uri(server.uri.clone())
+		.with_events_uri(server.uri.clone())
+		.with_diagnostic_opt_out(true)
+}
+
+fn client_with(config: LDConfig) -> LDClient {
+	let client = LDClient::new("sdk-key".to_owned(), config).unwrap();
+	assert!(client.initialized());
+	client
+}
+
+fn client(server: &TestServer) -> LDClient {
+	client_with(config(server))
+}
+
+// Closes the client, which delivers its events, and returns everything that was posted.
+fn posted_events(mut client: LDClient, server: &TestServer) -> Vec<Value> {
+	client.close();
+	let mut events = vec![];
+	while let Some(request) = server.next_request() {
+		if request.path == "/bulk" {
+			events.extend(request.json().as_array().unwrap().iter().cloned());
+			return events;
+		}
+	}
+	panic!("no events were posted");
+}
+
+fn events_of_kind<'a>(events: &'a [Value], kind: &str) -> Vec<&'a Value> {
+	events.iter().filter(|e| e["kind"] == json!(kind)).collect()
+}
+
+fn user() -> LDUser {
+	LDUser::new("user-key".to_owned())
+}
+
+#[test]
+fn prerequisite_feature_event_has_prereq_of_and_no_default() {
+	let server = start_server();
+	let c = client(&server);
+	assert_eq!(json!("on"), c.variation(&"parent".to_owned(), &user(), json!("parent-default")));
+	let events = posted_events(c, &server);
+	let features = events_of_kind(&events, "feature");
+	assert_eq!(2, features.len());
+	let prereq = features[0];
+	assert_eq!(json!("prereq"), prereq["key"]);
+	assert_eq!(json!("parent"), prereq["prereqOf"]);
+	assert_eq!(json!("on"), prereq["value"]);
+	assert_eq!(json!(1), prereq["variation"]);
+	assert!(prereq.get("default").is_none());
+	let parent = features[1];
+	assert_eq!(json!("parent"), parent["key"]);
+	assert!(parent.get("prereqOf").is_none());
+	assert_eq!(json!("parent-default"), parent["default"]);
+}
+
+#[test]
+fn summary_counts_prerequisite_evaluations() {
+	let server = start_server();
+	let c = client(&server);
+	c.variation(&"parent".to_owned(), &user(), json!("parent-default"));
+	let events = posted_events(c, &server);
+	let summary = events_of_kind(&events, "summary")[0];
+	assert_eq!(json!({
+		"default": null,
+		"counters": [{ "value": "on", "variation": 1, "version": 1, "count": 1 }]
+	}), summary["features"]["prereq"]);
+	assert_eq!(json!("parent-default"), summary["features"]["parent"]["default"]);
+}
+
+#[test]
+fn summary_default_comes_from_later_direct_evaluation() {
+	let server = start_server();
+	let c = client(&server);
+	c.variation(&"parent".to_owned(), &user(), json!("parent-default"));
+	c.variation(&"prereq".to_owned(), &user(), json!("prereq-default"));
+	let events = posted_events(c, &server);
+	let summary = events_of_kind(&events, "summary")[0];
+	assert_eq!(json!({
+		"default": "prereq-default",
+		"counters": [{ "value": "on", "variation": 1, "version": 1, "count": 2 }]
+	}), summary["features"]["prereq"]);
+}
+
+#[test]
+fn identify_event_has_the_whole_user() {
+	let server = start_server();
+	let c = client(&server);
+	let user = LDUser::builder("user-key".to_owned()).name("Name".to_owned()).custom_string("team", "a").build();
+	c.identify(&user);
+	let events = posted_events(c, &server);
+	assert_eq!(1, events.len());
+	let identify = &events[0];
+	assert_eq!(json!("identify"), identify["kind"]);
+	assert_eq!(json!("user-key"), identify["key"]);
+	assert!(identify["creationDate"].is_u64());
+	assert_eq!(json!({ "key": "user-key", "name": "Name", "custom": { "team": "a" } }), identify["user"]);
+	assert!(identify.get("userKey").is_none());
+}
+
+#[test]
+fn custom_event_has_data_and_metric_value() {
+	let server = start_server();
+	let c = client(&server);
+	c.track("purchase", &user(), Some(json!({ "item": "hat", "sizes": [1, 2] })), Some(9.99));
+	let events = posted_events(c, &server);
+	let custom = events_of_kind(&events, "custom");
+	assert_eq!(1, custom.len());
+	let e = custom[0];
+	assert_eq!(json!("purchase"), e["key"]);
+	assert_eq!(json!("user-key"), e["userKey"]);
+	assert_eq!(json!({ "item": "hat", "sizes": [1, 2] }), e["data"]);
+	assert_eq!(json!(9.99), e["metricValue"]);
+	assert!(e["creationDate"].is_u64());
+	assert!(e.get("user").is_none());
+	// the user is sent once, in an index event
+	assert_eq!(1, events_of_kind(&events, "index").len());
+}
+
+#[test]
+fn custom_event_without_data_or_metric_value_leaves_them_out() {
+	let server = start_server();
+	let c = client(&server);
+	c.track("click", &user(), None, None);
+	let events = posted_events(c, &server);
+	let e = events_of_kind(&events, "custom")[0];
+	assert_eq!(json!("click"), e["key"]);
+	assert!(e.get("data").is_none());
+	assert!(e.get("metricValue").is_none());
+}
+
+#[test]
+fn index_event_is_sent_only_the_first_time_a_user_is_seen() {
+	let server = start_server();
+	let c = client(&server);
+	let other = LDUser::new("other-key".to_owned());
+	c.variation(&"prereq".to_owned(), &user(), json!("default"));
+	c.variation(&"prereq".to_owned(), &user(), json!("default"));
+	c.track("click", &user(), None, None);
+	c.variation(&"prereq".to_owned(), &other, json!("default"));
+	let events = posted_events(c, &server);
+	let index = events_of_kind(&events, "index");
+	assert_eq!(2, index.len());
+	assert_eq!(json!({ "key": "user-key" }), index[0]["user"]);
+	assert_eq!(json!({ "key": "other-key" }), index[1]["user"]);
+	// the index event comes before the first event that refers to the user
+	assert_eq!(json!("index"), events[0]["kind"]);
+	assert_eq!(json!("feature"), events[1]["kind"]);
+}
+
+#[test]
+fn feature_event_refers_to_user_by_key() {
+	let server = start_server();
+	let c = client(&server);
+	c.variation(&"prereq".to_owned(), &user(), json!("default"));
+	let events = posted_events(c, &server);
+	let feature = events_of_kind(&events, "feature")[0];
+	assert_eq!(json!("user-key"), feature["userKey"]);
+	assert!(feature.get("user").is_none());
+	assert!(feature.get("contextKeys").is_none());
+}
+
+#[test]
+fn user_keys_are_forgotten_after_flush_interval() {
+	let server = start_server();
+	let c = client_with(config(&server).with_user_keys_flush_interval_millis(100));
+	c.variation(&"prereq".to_owned(), &user(), json!("default"));
+	thread::sleep(Duration::from_millis(500));
+	c.variation(&"prereq".to_owned(), &user(), json!("default"));
+	let events = posted_events(c, &server);
+	assert_eq!(2, events_of_kind(&events, "index").len());
+}
+
+// A flag that isn't tracked itself, with the given changes.
+fn untracked_flag(key: &str, changes: Value) -> Value {
+	let mut f = flag(key, json!([]));
+	f["trackEvents"] = json!(false);
+	for (k, v) in changes.as_object().unwrap() {
+		f[k] = v.clone();
+	}
+	f
+}
+
+fn start_experiment_server() -> TestServer {
+	start_server_with(json!({
+		"experiment": untracked_flag("experiment", json!({
+			"fallthrough": { "rollout": { "kind": "experiment", "variations": [{ "variation": 1, "weight": 100000 }] } }
+		})),
+		"tracked-fallthrough": untracked_flag("tracked-fallthrough", json!({ "trackEventsFallthrough": true })),
+		"tracked-rule": untracked_flag("tracked-rule", json!({
+			"rules": [{
+				"id": "rule-id",
+				"clauses": [{ "attribute": "key", "op": "in", "values": ["user-key"], "negate": false }],
+				"variation": 1,
+				"trackEvents": true
+			}]
+		})),
+		"untracked": untracked_flag("untracked", json!({}))
+	}))
+}
+
+// The full feature event for the flag, if there was one.
+fn feature_event<'a>(events: &'a [Value], key: &str) -> Option<&'a Value> {
+	events_of_kind(events, "feature").into_iter().find(|e| e["key"] == json!(key))
+}
+
+#[test]
+fn experiment_rollout_gives_feature_event_with_reason() {
+	let server = start_experiment_server();
+	let c = client(&server);
+	assert_eq!(json!("on"), c.variation(&"experiment".to_owned(), &user(), json!("default")));
+	let events = posted_events(c, &server);
+	let e = feature_event(&events, "experiment").unwrap();
+	assert_eq!(json!(1), e["variation"]);
+	assert_eq!(json!({ "kind": "FALLTHROUGH", "inExperiment": true }), e["reason"]);
+}
+
+#[test]
+fn tracked_fallthrough_gives_feature_event_with_reason() {
+	let server = start_experiment_server();
+	let c = client(&server);
+	c.variation(&"tracked-fallthrough".to_owned(), &user(), json!("default"));
+	c.variation(&"tracked-rule".to_owned(), &user(), json!("default"));
+	let events = posted_events(c, &server);
+	assert_eq!(json!({ "kind": "FALLTHROUGH" }), feature_event(&events, "tracked-fallthrough").unwrap()["reason"]);
+	assert_eq!(json!({ "kind": "RULE_MATCH", "ruleIndex": 0, "ruleId": "rule-id" }),
+		feature_event(&events, "tracked-rule").unwrap()["reason"]);
+}
+
+#[test]
+fn untracked_flag_gives_only_summary() {
+	let server = start_experiment_server();
+	let c = client(&server);
+	c.variation(&"untracked".to_owned(), &user(), json!("default"));
+	let events = posted_events(c, &server);
+	assert!(feature_event(&events, "untracked").is_none());
+	assert_eq!(1, events_of_kind(&events, "summary")[0]["features"]["untracked"]["counters"][0]["count"]);
+}
@@ -0,0 +1,111 @@
+// Tests for the bootstrap data that all_flags_state gives the JavaScript SDK.
+
+extern crate ldclient;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use ldclient::client::LDClient;
+use ldclient::config::LDConfig;
+use ldclient::feature_store::FeatureStore;
+use ldclient::flag::FeatureFlag;
+use ldclient::flags_state::FlagsStateOptions;
+use ldclient::user::LDUser;
+
+
+fn flag(key: &str, version: u32, extra: Value) -> FeatureFlag {
+	let mut flag = common::flag(extra);
+	flag.key = key.to_owned();
+	flag.version = version;
+	flag.fallthrough.variation = Some(1);
+	flag.variations = vec![json!("off"), json!("on")];
+	flag
+}
+
+fn store() -> FeatureStore {
+	let mut flags = HashMap::new();
+	flags.insert("plain".to_owned(), flag("plain", 10, json!({})));
+	flags.insert("tracked".to_owned(), flag("tracked", 20, json!({ "trackEvents": true })));
+	flags.insert("client".to_owned(), flag("client", 30,
+		json!({ "clientSideAvailability": { "usingEnvironmentId": true } })));
+	flags.insert("experiment".to_owned(), flag("experiment", 40, json!({ "trackEventsFallthrough": true })));
+	flags.insert("debugged".to_owned(), flag("debugged", 50, json!({ "debugEventsUntilDate": 32503680000000u64 })));
+	let store = FeatureStore::in_memory_store();
+	store.init(flags);
+	store
+}
+
+fn client() -> LDClient {
+	LDClient::new("sdk-key".to_owned(), LDConfig::default().with_offline(true).with_feature_store_factory(&store))
+		.unwrap()
+}
+
+fn state_json(options: FlagsStateOptions) -> Value {
+	client().all_flags_state(&LDUser::new("user-key".to_owned()), &options).to_json()
+}
+
+#[test]
+fn default_options() {
+	assert_eq!(json!({
+		"plain": "on",
+		"tracked": "on",
+		"client": "on",
+		"experiment": "on",
+		"debugged": "on",
+		"$flagsState": {
+			"plain": { "variation": 1, "version": 10 },
+			"tracked": { "variation": 1, "version": 20, "trackEvents": true },
+			"client": { "variation": 1, "version": 30 },
+			"experiment": { "variation": 1, "version": 40, "trackEvents": true, "trackReason": true,
+				"reason": { "kind": "FALLTHROUGH" } },
+			"debugged": { "variation": 1, "version": 50, "debugEventsUntilDate": 32503680000000u64 }
+		},
+		"$valid": true
+	}), state_json(FlagsStateOptions::default()));
+}
+
+#[test]
+fn client_side_only() {
+	let json = state_json(FlagsStateOptions::default().with_client_side_only(true));
+	assert_eq!(json!({
+		"client": "on",
+		"$flagsState": { "client": { "variation": 1, "version": 30 } },
+		"$valid": true
+	}), json);
+}
+
+#[test]
+fn with_reasons() {
+	let json = state_json(FlagsStateOptions::default().with_reasons(true));
+	for key in &["plain", "tracked", "client", "experiment", "debugged"] {
+		assert_eq!(json!({ "kind": "FALLTHROUGH" }), json["$flagsState"][key]["reason"], "{}", key);
+	}
+}
+
+#[test]
+fn details_only_for_tracked_flags() {
+	let options = FlagsStateOptions::default().with_reasons(true).with_details_only_for_tracked_flags(true);
+	let json = state_json(options);
+	assert_eq!(json!({ "variation": 1 }), json["$flagsState"]["plain"]);
+	assert_eq!(json!({ "variation": 1 }), json["$flagsState"]["client"]);
+	assert_eq!(json!({ "variation": 1, "version": 20, "trackEvents": true, "reason": { "kind": "FALLTHROUGH" } }),
+		json["$flagsState"]["tracked"]);
+	assert_eq!(json!({ "variation": 1, "version": 40, "trackEvents": true, "trackReason": true,
+		"reason": { "kind": "FALLTHROUGH" } }), json["$flagsState"]["experiment"]);
+	assert_eq!(json!({ "variation": 1, "version": 50, "debugEventsUntilDate": 32503680000000u64,
+		"reason": { "kind": "FALLTHROUGH" } }), json["$flagsState"]["debugged"]);
+	assert_eq!("on", json["plain"]);
+}
+
+#[test]
+fn invalid_when_client_has_no_flags() {
+	let c = LDClient::new("sdk-key".to_owned(), LDConfig::default().with_offline(true)).unwrap();
+	let state = c.all_flags_state(&LDUser::new("user-key".to_owned()), &FlagsStateOptions::default());
+	assert!(!state.is_valid());
+	assert_eq!(json!({ "$flagsState": {}, "$valid": false }), state.to_json());
+}
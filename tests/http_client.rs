@@ -0,0 +1,144 @@
+// Tests for the headers that the shared HTTP client sends on every request, both when polling
+// for flags and when posting events, and for the HTTP configuration errors that it reports.
+
+extern crate flate2;
+extern crate ldclient;
+extern crate reqwest;
+#[macro_use]
+extern crate serde_json;
+extern crate tiny_http;
+
+mod test_server;
+
+use ldclient::client::LDClient;
+use ldclient::config::{HttpConfig, LDConfig};
+use ldclient::event_processor::EventSender;
+use ldclient::requestor::{make_http_client, Requestor, USER_AGENT};
+
+use test_server::{ReceivedRequest, TestServer};
+
+
+fn flags_server() -> TestServer {
+	TestServer::start(|request| {
+		if request.path == "/sdk/latest-all" {
+			(200, json!({ "flags": {} }).to_string())
+		} else {
+			(202, String::new())
+		}
+	})
+}
+
+// Polls for flags and posts events with a client made from the config, returning both requests.
+fn requests_with(config: &LDConfig) -> Vec<ReceivedRequest> {
+	let server = flags_server();
+	let client = make_http_client(config).unwrap();
+	Requestor::new("sdk-key".to_owned(), &server.uri, client.clone()).get_all_flags().unwrap();
+	let sender = EventSender::new("sdk-key".to_owned(), &server.uri, client);
+	assert!(sender.send_events(&json!([{ "kind": "custom", "key": "a" }])).is_ok());
+	let requests = vec![server.next_request().unwrap(), server.next_request().unwrap()];
+	assert_eq!("/sdk/latest-all", requests[0].path);
+	assert_eq!("/bulk", requests[1].path);
+	requests
+}
+
+#[test]
+fn user_agent_is_sent() {
+	for request in requests_with(&LDConfig::default()) {
+		assert_eq!(Some(USER_AGENT), request.header("User-Agent"), "{}", request.path);
+		assert!(USER_AGENT.starts_with("RustClient/"));
+		assert_eq!(None, request.header("X-LaunchDarkly-Wrapper"), "{}", request.path);
+		assert_eq!(None, request.header("X-LaunchDarkly-Tags"), "{}", request.path);
+	}
+}
+
+#[test]
+fn wrapper_is_sent() {
+	let config = LDConfig::default().with_wrapper("my-wrapper".to_owned(), Some("1.2.3".to_owned()));
+	for request in requests_with(&config) {
+		assert_eq!(Some("my-wrapper/1.2.3"), request.header("X-LaunchDarkly-Wrapper"), "{}", request.path);
+	}
+	let config = LDConfig::default().with_wrapper("my-wrapper".to_owned(), None);
+	for request in requests_with(&config) {
+		assert_eq!(Some("my-wrapper"), request.header("X-LaunchDarkly-Wrapper"), "{}", request.path);
+	}
+}
+
+#[test]
+fn application_tags_are_sent() {
+	let config = LDConfig::default().with_application_info(Some("my-app".to_owned()), Some("1.0_beta.2".to_owned()));
+	for request in requests_with(&config) {
+		assert_eq!(Some("application-id/my-app application-version/1.0_beta.2"), request.header("X-LaunchDarkly-Tags"),
+			"{}", request.path);
+	}
+	let config = LDConfig::default().with_application_info(None, Some("2".to_owned()));
+	for request in requests_with(&config) {
+		assert_eq!(Some("application-version/2"), request.header("X-LaunchDarkly-Tags"), "{}", request.path);
+	}
+}
+
+#[test]
+fn invalid_application_tags_are_left_out() {
+	let too_long = "a".repeat(65);
+	let cases = [
+		(Some("my app"), Some("1.0"), Some("application-version/1.0")),
+		(Some("my-app"), Some(too_long.as_str()), Some("application-id/my-app")),
+		(Some(""), Some("1.0/2"), None),
+		(Some("é"), None, None)
+	];
+	for &(id, version, expected) in cases.iter() {
+		let config = LDConfig::default().with_application_info(id.map(str::to_owned), version.map(str::to_owned));
+		for request in requests_with(&config) {
+			assert_eq!(expected, request.header("X-LaunchDarkly-Tags"), "{:?} {:?} {}", id, version, request.path);
+		}
+	}
+	let longest = "a".repeat(64);
+	let config = LDConfig::default().with_application_info(Some(longest.clone()), None);
+	for request in requests_with(&config) {
+		assert_eq!(Some(format!("application-id/{}", longest).as_str()), request.header("X-LaunchDarkly-Tags"));
+	}
+}
+
+#[test]
+fn custom_headers_are_sent() {
+	let http = HttpConfig::default()
+		.with_header("X-Custom".to_owned(), "custom value".to_owned())
+		.with_header("X-Other".to_owned(), "other".to_owned());
+	for request in requests_with(&LDConfig::default().with_http(http)) {
+		assert_eq!(Some("custom value"), request.header("X-Custom"), "{}", request.path);
+		assert_eq!(Some("other"), request.header("X-Other"), "{}", request.path);
+	}
+}
+
+fn http_client_error(http: HttpConfig) -> String {
+	match make_http_client(&LDConfig::default().with_http(http)) {
+		Ok(_) => panic!("expected an error"),
+		Err(e) => e
+	}
+}
+
+#[test]
+fn invalid_proxy_url_is_an_error() {
+	let error = http_client_error(HttpConfig::default().with_proxy("not a url".to_owned()));
+	assert!(error.starts_with("invalid proxy URL \"not a url\""), "{}", error);
+}
+
+#[test]
+fn invalid_root_certificate_is_an_error() {
+	let pem = b"-----BEGIN CERTIFICATE-----\nnot base64!\n-----END CERTIFICATE-----\n".to_vec();
+	let error = http_client_error(HttpConfig::default().with_root_certificate(pem));
+	assert!(error.starts_with("invalid root certificate"), "{}", error);
+}
+
+#[test]
+fn invalid_header_name_or_value_is_an_error() {
+	let error = http_client_error(HttpConfig::default().with_header("Bad Name".to_owned(), "value".to_owned()));
+	assert_eq!("invalid header name \"Bad Name\"", error);
+	let error = http_client_error(HttpConfig::default().with_header("X-Custom".to_owned(), "bad\nvalue".to_owned()));
+	assert_eq!("invalid value for header \"X-Custom\"", error);
+}
+
+#[test]
+fn invalid_http_config_stops_the_client_starting() {
+	let config = LDConfig::default().with_offline(true).with_http(HttpConfig::default().with_proxy("not a url".to_owned()));
+	assert!(LDClient::new("sdk-key".to_owned(), config).is_err());
+}
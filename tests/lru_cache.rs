@@ -0,0 +1,65 @@
+// Tests for the cache that remembers which users the event processor has already indexed.
+
+extern crate ldclient;
+
+use ldclient::lru_cache::LruCache;
+
+
+#[test]
+fn remembers_keys_it_has_seen() {
+	let mut cache = LruCache::new(10);
+	assert!(cache.is_empty());
+	assert!(!cache.notice("a"));
+	assert!(cache.notice("a"));
+	assert!(!cache.notice("b"));
+	assert_eq!(2, cache.len());
+	assert!(!cache.is_empty());
+}
+
+#[test]
+fn evicts_least_recently_used_key_when_full() {
+	let mut cache = LruCache::new(2);
+	cache.notice("a");
+	cache.notice("b");
+	assert!(!cache.notice("c"));
+	assert_eq!(2, cache.len());
+	assert!(cache.notice("c"));
+	assert!(cache.notice("b"));
+	assert!(!cache.notice("a"));
+}
+
+#[test]
+fn re_touching_a_key_makes_it_most_recently_used() {
+	let mut cache = LruCache::new(2);
+	cache.notice("a");
+	cache.notice("b");
+	assert!(cache.notice("a"));
+	// "b" is now the least recently used, so it's the one that goes
+	cache.notice("c");
+	assert!(cache.notice("a"));
+	assert!(cache.notice("c"));
+	assert!(!cache.notice("b"));
+	assert_eq!(2, cache.len());
+}
+
+#[test]
+fn capacity_zero_remembers_nothing() {
+	let mut cache = LruCache::new(0);
+	assert!(!cache.notice("a"));
+	assert!(!cache.notice("a"));
+	assert!(cache.is_empty());
+	assert_eq!(0, cache.len());
+}
+
+#[test]
+fn clear_forgets_everything() {
+	let mut cache = LruCache::new(2);
+	cache.notice("a");
+	cache.notice("b");
+	cache.clear();
+	assert!(cache.is_empty());
+	assert!(!cache.notice("a"));
+	assert!(!cache.notice("b"));
+	assert!(!cache.notice("c"));
+	assert_eq!(2, cache.len());
+}
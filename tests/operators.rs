@@ -0,0 +1,160 @@
+// Tests for parsing clause operators, for operators that this SDK doesn't know, for how the "in"
+// operator compares values, and for the startsWith and endsWith operators.
+
+extern crate ldclient;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use ldclient::client::LDClient;
+use ldclient::config::LDConfig;
+use ldclient::evaluation::{EvaluationReason, ErrorKind};
+use ldclient::feature_store::FeatureStore;
+use ldclient::flag::{FeatureFlag, Operator};
+use ldclient::user::LDUser;
+
+
+#[test]
+fn parses_operator_names() {
+	let cases = [
+		("in", Operator::In),
+		("endsWith", Operator::EndsWith),
+		("startsWith", Operator::StartsWith),
+		("matches", Operator::Matches),
+		("contains", Operator::Contains),
+		("lessThan", Operator::LessThan),
+		("lessThanOrEqual", Operator::LessThanOrEqual),
+		("greaterThan", Operator::GreaterThan),
+		("greaterThanOrEqual", Operator::GreaterThanOrEqual),
+		("before", Operator::Before),
+		("after", Operator::After),
+		("semVerEqual", Operator::SemVerEqual),
+		("semVerLessThan", Operator::SemVerLessThan),
+		("semVerGreaterThan", Operator::SemVerGreaterThan)
+	];
+	for &(name, ref op) in cases.iter() {
+		assert_eq!(*op, Operator::from(name.to_string()));
+		assert_eq!(*op, serde_json::from_value::<Operator>(json!(name)).unwrap());
+	}
+}
+
+#[test]
+fn unrecognized_operator_names_are_kept() {
+	for name in &["segmentMatch", "IN", "", "in "] {
+		assert_eq!(Operator::Unknown(name.to_string()), Operator::from(name.to_string()));
+	}
+	let flag = common::clause_flag(json!({ "attribute": "key", "op": "futureOp", "values": [], "negate": false }));
+	assert_eq!(Operator::Unknown("futureOp".to_owned()), flag.rules[0].clauses[0].op);
+}
+
+fn unknown_operator_flag(key: &str, rules: Value) -> FeatureFlag {
+	common::flag(json!({ "key": key, "rules": rules, "variations": ["fallthrough", "rule"], "offVariation": 0 }))
+}
+
+fn unknown_operator_store() -> FeatureStore {
+	let unknown = json!({ "attribute": "key", "op": "futureOp", "values": ["user-key"], "negate": false });
+	let matching = json!({ "attribute": "key", "op": "in", "values": ["user-key"], "negate": false });
+	let mut flags = HashMap::new();
+	flags.insert("unknown".to_owned(), unknown_operator_flag("unknown", json!([
+		{ "clauses": [unknown], "variation": 1 }
+	])));
+	// the rule with the unknown operator is never reached, because an earlier rule matches
+	flags.insert("unreached".to_owned(), unknown_operator_flag("unreached", json!([
+		{ "clauses": [matching], "variation": 1 },
+		{ "clauses": [unknown], "variation": 1 }
+	])));
+	let store = FeatureStore::in_memory_store();
+	store.init(flags);
+	store
+}
+
+fn client() -> LDClient {
+	let config = LDConfig::default().with_offline(true).with_feature_store_factory(&unknown_operator_store);
+	LDClient::new("sdk-key".to_owned(), config).unwrap()
+}
+
+#[test]
+fn unknown_operator_gives_malformed_flag_and_default() {
+	let detail = client().variation_detail(&"unknown".to_owned(), &LDUser::new("user-key".to_owned()), json!("default"));
+	assert_eq!(json!("default"), detail.value);
+	assert_eq!(None, detail.variation_index);
+	assert_eq!(EvaluationReason::Error { error_kind: ErrorKind::MalformedFlag }, detail.reason);
+}
+
+#[test]
+fn unknown_operator_that_is_never_reached_is_not_an_error() {
+	let detail = client().variation_detail(&"unreached".to_owned(), &LDUser::new("user-key".to_owned()), json!("default"));
+	assert_eq!(json!("rule"), detail.value);
+	assert_eq!(Some(1), detail.variation_index);
+}
+
+// Returns true if a user whose "attr" attribute is user_value matches an "in" clause with the given value.
+fn in_matches(user_value: Value, clause_value: Value) -> bool {
+	common::clause_matches("attr", user_value, "in", clause_value)
+}
+
+#[test]
+fn in_compares_numbers_by_value() {
+	assert!(in_matches(json!(1), json!(1.0)));
+	assert!(in_matches(json!(1.0), json!(1)));
+	assert!(in_matches(json!(-3), json!(-3.0)));
+	assert!(!in_matches(json!(1), json!(1.5)));
+}
+
+#[test]
+fn in_does_not_convert_between_numbers_and_strings() {
+	assert!(!in_matches(json!("1"), json!(1)));
+	assert!(!in_matches(json!(1), json!("1")));
+	assert!(!in_matches(json!(true), json!("true")));
+	assert!(in_matches(json!("1"), json!("1")));
+}
+
+#[test]
+fn in_compares_objects_and_nested_numbers_by_value() {
+	assert!(in_matches(json!({ "a": 1, "b": [2] }), json!({ "b": [2.0], "a": 1.0 })));
+	assert!(!in_matches(json!({ "a": 1 }), json!({ "a": 1, "b": 2 })));
+	assert!(!in_matches(json!({ "a": 1 }), json!({ "a": "1" })));
+}
+
+#[test]
+fn in_matches_any_element_of_an_array_attribute() {
+	assert!(in_matches(json!(["x", 2]), json!(2.0)));
+	assert!(!in_matches(json!(["x", "2"]), json!(2)));
+}
+
+#[test]
+fn starts_with_matches_prefix_of_user_value() {
+	let starts_with = |user_value: Value, clause_value: Value| {
+		common::clause_matches("attr", user_value, "startsWith", clause_value)
+	};
+	assert!(starts_with(json!("prefix-rest"), json!("prefix")));
+	assert!(starts_with(json!("prefix"), json!("prefix")));
+	assert!(starts_with(json!("anything"), json!("")));
+	assert!(!starts_with(json!("rest-prefix"), json!("prefix")));
+	// the clause value is the prefix, not the user value
+	assert!(!starts_with(json!("pre"), json!("prefix")));
+	assert!(!starts_with(json!("Prefix-rest"), json!("prefix")));
+	assert!(!starts_with(json!(12), json!("1")));
+	assert!(!starts_with(json!("12"), json!(1)));
+}
+
+#[test]
+fn ends_with_matches_suffix_of_user_value() {
+	let ends_with = |user_value: Value, clause_value: Value| {
+		common::clause_matches("attr", user_value, "endsWith", clause_value)
+	};
+	assert!(ends_with(json!("rest-suffix"), json!("suffix")));
+	assert!(ends_with(json!("suffix"), json!("suffix")));
+	assert!(ends_with(json!("anything"), json!("")));
+	assert!(!ends_with(json!("suffix-rest"), json!("suffix")));
+	// the clause value is the suffix, not the user value
+	assert!(!ends_with(json!("fix"), json!("suffix")));
+	assert!(!ends_with(json!("rest-Suffix"), json!("suffix")));
+	assert!(!ends_with(json!(21), json!("1")));
+	assert!(!ends_with(json!("21"), json!(1)));
+}
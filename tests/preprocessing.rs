@@ -0,0 +1,138 @@
+// Checks that preprocessing a flag, as the store does for every flag it receives, doesn't change
+// the results of evaluating it.
+
+extern crate ldclient;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use serde_json::Value;
+
+use ldclient::context::LDContext;
+use ldclient::feature_store::FeatureStore;
+use ldclient::flag::FeatureFlag;
+use ldclient::user::LDUser;
+
+
+fn flag(rules: Value, fallthrough: Value) -> FeatureFlag {
+	common::flag(json!({ "rules": rules, "fallthrough": fallthrough, "variations": ["a", "b", "c"] }))
+}
+
+fn clause_flag(attribute: &str, op: &str, values: Value) -> FeatureFlag {
+	flag(json!([{
+		"clauses": [{ "attribute": attribute, "op": op, "values": values, "negate": false }],
+		"variation": 1
+	}]), json!({ "variation": 0 }))
+}
+
+// Evaluates the flag both as it was parsed and after preprocessing, which must agree.
+fn evaluate(flag: &FeatureFlag, user: &LDUser) -> Value {
+	let store = FeatureStore::in_memory_store();
+	let context = LDContext::from(user);
+	let unprocessed = flag.evaluate(&context, &store).0.value;
+	let mut preprocessed = flag.clone();
+	preprocessed.preprocess();
+	assert_eq!(unprocessed, preprocessed.evaluate(&context, &store).0.value);
+	unprocessed
+}
+
+fn user_with(name: &str, value: Value) -> LDUser {
+	LDUser::builder("user-key".to_owned()).custom(name, value).build()
+}
+
+#[test]
+fn malformed_regex_never_matches() {
+	let f = clause_flag("attr", "matches", json!(["[", "(a"]));
+	assert_eq!(json!("a"), evaluate(&f, &user_with("attr", json!("["))));
+	assert_eq!(json!("a"), evaluate(&f, &user_with("attr", json!("(a"))));
+}
+
+#[test]
+fn malformed_regex_does_not_stop_other_values_matching() {
+	let f = clause_flag("attr", "matches", json!(["[", 3, "^x"]));
+	assert_eq!(json!("b"), evaluate(&f, &user_with("attr", json!("xyz"))));
+	assert_eq!(json!("a"), evaluate(&f, &user_with("attr", json!("3"))));
+}
+
+#[test]
+fn malformed_date_never_matches() {
+	for op in &["before", "after"] {
+		let f = clause_flag("date", op, json!(["not a date", true, "2020-13-01T00:00:00Z", 1.2630441768696799e30]));
+		for user_value in &[json!("2000-01-01T00:00:00Z"), json!("2040-01-01T00:00:00Z"), json!(0)] {
+			assert_eq!(json!("a"), evaluate(&f, &user_with("date", user_value.clone())), "{} {}", op, user_value);
+		}
+	}
+}
+
+#[test]
+fn malformed_date_does_not_stop_other_values_matching() {
+	let f = clause_flag("date", "before", json!(["not a date", "2030-01-01T00:00:00Z"]));
+	assert_eq!(json!("b"), evaluate(&f, &user_with("date", json!("2020-01-01T00:00:00Z"))));
+}
+
+#[test]
+fn malformed_semver_never_matches() {
+	for op in &["semVerEqual", "semVerLessThan", "semVerGreaterThan"] {
+		let f = clause_flag("version", op, json!(["1.x", "", "a.b.c", 2]));
+		for user_value in &["0.0.1", "1.0.0", "3.0.0"] {
+			assert_eq!(json!("a"), evaluate(&f, &user_with("version", json!(user_value))), "{} {}", op, user_value);
+		}
+	}
+}
+
+#[test]
+fn malformed_semver_does_not_stop_other_values_matching() {
+	let f = clause_flag("version", "semVerEqual", json!(["1.x", "2.0.0"]));
+	assert_eq!(json!("b"), evaluate(&f, &user_with("version", json!("2.0.0"))));
+}
+
+#[test]
+fn nested_clause_attribute_is_looked_up() {
+	let f = flag(json!([{
+		"clauses": [{ "contextKind": "user", "attribute": "/address/city", "op": "in", "values": ["Oakland"], "negate": false }],
+		"variation": 1
+	}]), json!({ "variation": 0 }));
+	assert_eq!(json!("b"), evaluate(&f, &user_with("address", json!({ "city": "Oakland" }))));
+	assert_eq!(json!("a"), evaluate(&f, &user_with("address", json!({ "city": "Berkeley" }))));
+}
+
+fn rollout(extra: Value) -> Value {
+	let mut rollout = json!({
+		"variations": [
+			{ "variation": 1, "weight": 50000 },
+			{ "variation": 2, "weight": 50000 }
+		]
+	});
+	for (k, v) in extra.as_object().unwrap() {
+		rollout[k] = v.clone();
+	}
+	rollout
+}
+
+#[test]
+fn bucket_by_is_the_same_after_preprocessing() {
+	let fallthrough = flag(json!([]), json!({ "rollout": rollout(json!({ "bucketBy": "/address/zip" })) }));
+	let rule = flag(json!([{
+		"clauses": [{ "attribute": "key", "op": "in", "values": ["user-key"], "negate": false }],
+		"rollout": rollout(json!({ "bucketBy": "team" }))
+	}]), json!({ "variation": 0 }));
+	for i in 0..20 {
+		let user = LDUser::builder("user-key".to_owned())
+			.custom("address", json!({ "zip": format!("zip{}", i) }))
+			.custom_string("team", &format!("team{}", i))
+			.build();
+		evaluate(&fallthrough, &user);
+		evaluate(&rule, &user);
+	}
+}
+
+#[test]
+fn experiment_buckets_by_key_after_preprocessing() {
+	let by_team = flag(json!([]), json!({ "rollout": rollout(json!({ "kind": "experiment", "bucketBy": "team" })) }));
+	let by_key = flag(json!([]), json!({ "rollout": rollout(json!({ "kind": "experiment" })) }));
+	for i in 0..20 {
+		let user = LDUser::builder(format!("user{}", i)).custom_string("team", "same").build();
+		assert_eq!(evaluate(&by_key, &user), evaluate(&by_team, &user));
+	}
+}
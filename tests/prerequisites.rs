@@ -0,0 +1,168 @@
+// Tests for evaluating flags with prerequisites, including flag data with prerequisite cycles,
+// which must give an error and the caller's default value rather than any of the flag's values.
+
+extern crate ldclient;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use ldclient::client::LDClient;
+use ldclient::config::LDConfig;
+use ldclient::context::LDContext;
+use ldclient::evaluation::{EvaluationDetail, EvaluationReason, ErrorKind};
+use ldclient::event::FeatureRequestEvent;
+use ldclient::feature_store::FeatureStore;
+use ldclient::flag::FeatureFlag;
+use ldclient::user::LDUser;
+
+
+// A flag that is on, requires variation 1 of each of the prerequisites, and serves variation 1
+// ("fallthrough") if they all pass.
+fn flag(key: &str, prerequisites: &[&str]) -> FeatureFlag {
+	let prereqs: Vec<Value> = prerequisites.iter().map(|k| json!({ "key": k, "variation": 1 })).collect();
+	common::flag(json!({
+		"key": key,
+		"prerequisites": prereqs,
+		"fallthrough": { "variation": 1 },
+		"variations": ["off", "fallthrough"]
+	}))
+}
+
+fn store_with(flags: Vec<FeatureFlag>) -> FeatureStore {
+	let store = FeatureStore::in_memory_store();
+	store.init(flags.into_iter().map(|f| (f.key.clone(), f)).collect::<HashMap<String, FeatureFlag>>());
+	store
+}
+
+fn cycle_store() -> FeatureStore {
+	store_with(vec![flag("a", &["b"]), flag("b", &["a"]), flag("c", &["a"])])
+}
+
+fn self_reference_store() -> FeatureStore {
+	store_with(vec![flag("a", &["a"])])
+}
+
+// Flags chain0 ... chainN, where each requires the next one.
+fn chain(length: usize) -> Vec<FeatureFlag> {
+	(0..length).map(|i| {
+		let next = format!("chain{}", i + 1);
+		let prereqs: Vec<&str> = if i + 1 < length { vec![&next] } else { vec![] };
+		flag(&format!("chain{}", i), &prereqs)
+	}).collect()
+}
+
+fn deep_store() -> FeatureStore {
+	store_with(chain(150))
+}
+
+fn shallow_store() -> FeatureStore {
+	store_with(chain(50))
+}
+
+fn client(factory: &'static dyn Fn() -> FeatureStore) -> LDClient {
+	LDClient::new("sdk-key".to_owned(), LDConfig::default().with_offline(true).with_feature_store_factory(factory))
+		.unwrap()
+}
+
+fn evaluate(client: &LDClient, key: &str) -> EvaluationDetail {
+	client.variation_detail(&key.to_owned(), &LDUser::new("user-key".to_owned()), json!("default"))
+}
+
+fn malformed_flag_with_default() -> EvaluationDetail {
+	EvaluationDetail {
+		value: json!("default"),
+		variation_index: None,
+		reason: EvaluationReason::Error { error_kind: ErrorKind::MalformedFlag }
+	}
+}
+
+#[test]
+fn prerequisite_cycle_gives_default() {
+	let c = client(&cycle_store);
+	assert_eq!(malformed_flag_with_default(), evaluate(&c, "a"));
+	assert_eq!(malformed_flag_with_default(), evaluate(&c, "b"));
+}
+
+#[test]
+fn flag_depending_on_cycle_gives_default() {
+	let c = client(&cycle_store);
+	assert_eq!(malformed_flag_with_default(), evaluate(&c, "c"));
+}
+
+#[test]
+fn flag_that_is_its_own_prerequisite_gives_default() {
+	let c = client(&self_reference_store);
+	assert_eq!(malformed_flag_with_default(), evaluate(&c, "a"));
+}
+
+#[test]
+fn too_many_levels_of_prerequisites_gives_default() {
+	let c = client(&deep_store);
+	assert_eq!(malformed_flag_with_default(), evaluate(&c, "chain0"));
+	// nearer the end of the chain, there are few enough levels left
+	assert_eq!(json!("fallthrough"), evaluate(&c, "chain100").value);
+}
+
+#[test]
+fn many_levels_of_prerequisites_below_the_limit_are_fine() {
+	let c = client(&shallow_store);
+	let detail = evaluate(&c, "chain0");
+	assert_eq!(json!("fallthrough"), detail.value);
+	assert_eq!(Some(1), detail.variation_index);
+	assert_eq!(EvaluationReason::Fallthrough { in_experiment: false }, detail.reason);
+}
+
+#[test]
+fn cycle_gives_null_in_all_flags() {
+	let c = client(&cycle_store);
+	let values = c.all_flags(&LDUser::new("user-key".to_owned()));
+	assert_eq!(Some(&Value::Null), values.get("a"));
+}
+
+// Evaluates a flag directly, rather than through the client, to get the prerequisite events.
+fn evaluate_flag(store: &FeatureStore, key: &str) -> (EvaluationDetail, Vec<FeatureRequestEvent>) {
+	let flag = store.get(&key.to_owned()).unwrap();
+	flag.evaluate(&LDContext::from(&LDUser::new("user-key".to_owned())), store)
+}
+
+// A prerequisite whose variations both have the same value, serving the given one.
+fn equal_values_store(served: u32) -> FeatureStore {
+	let mut prereq = flag("prereq", &[]);
+	prereq.variations = vec![json!("same"), json!("same")];
+	prereq.fallthrough.variation = Some(served);
+	store_with(vec![flag("parent", &["prereq"]), prereq])
+}
+
+#[test]
+fn prerequisite_variation_is_compared_by_index_not_value() {
+	let (detail, _) = evaluate_flag(&equal_values_store(0), "parent");
+	assert_eq!(EvaluationReason::PrerequisiteFailed { prerequisite_key: "prereq".to_owned() }, detail.reason);
+	assert_eq!(Some(0), detail.variation_index);
+	let (detail, _) = evaluate_flag(&equal_values_store(1), "parent");
+	assert_eq!(EvaluationReason::Fallthrough { in_experiment: false }, detail.reason);
+	assert_eq!(Some(1), detail.variation_index);
+}
+
+#[test]
+fn off_prerequisite_fails_but_is_still_evaluated_and_recorded() {
+	// the off variation is the required one, but an off prerequisite fails anyway
+	let mut prereq = flag("prereq", &[]);
+	prereq.on = false;
+	prereq.offVariation = Some(1);
+	prereq.variations = vec![json!("a"), json!("b")];
+	let (detail, prereq_events) = evaluate_flag(&store_with(vec![flag("parent", &["prereq"]), prereq]), "parent");
+	assert_eq!(EvaluationReason::PrerequisiteFailed { prerequisite_key: "prereq".to_owned() }, detail.reason);
+	assert_eq!(json!("off"), detail.value);
+	assert_eq!(1, prereq_events.len());
+	let e = &prereq_events[0];
+	assert_eq!("prereq", e.key);
+	assert_eq!(Some("parent".to_owned()), e.prereqOf);
+	assert_eq!(json!("b"), e.value);
+	assert_eq!(Some(1), e.variation);
+	assert_eq!(None, e.default);
+}
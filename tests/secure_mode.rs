@@ -0,0 +1,56 @@
+
+// Known-answer tests for secure mode hashes, which must match what LaunchDarkly computes from the
+// SDK key, or the JavaScript SDK's requests are rejected.
+
+extern crate ldclient;
+
+use ldclient::client::LDClient;
+use ldclient::config::LDConfig;
+use ldclient::context::LDContext;
+use ldclient::user::LDUser;
+
+
+fn client(sdk_key: &str) -> LDClient {
+	LDClient::new(sdk_key.to_owned(), LDConfig::default().with_offline(true)).unwrap()
+}
+
+#[test]
+fn hash_matches_other_sdks() {
+	// the same test vector is used by the other server-side SDKs
+	let user = LDUser::new("Message".to_owned());
+	assert_eq!("aa747c502a898200f9e4fa21bac68136f886a0e27aec70ba06daf2e2a5cb5597",
+		client("secret").secure_mode_hash(&user));
+}
+
+#[test]
+fn hash_depends_only_on_key() {
+	let plain = LDUser::new("user-key".to_owned());
+	let detailed = LDUser::builder("user-key".to_owned())
+		.email("test@example.com".to_owned())
+		.custom_string("team", "a")
+		.build();
+	let c = client("sdk-12345");
+	assert_eq!("f2dfa88775090cd77e0a1417e4905f9b7ac74e878f38c580d4e292a45bf9dda2", c.secure_mode_hash(&plain));
+	assert_eq!(c.secure_mode_hash(&plain), c.secure_mode_hash(&detailed));
+}
+
+#[test]
+fn hash_of_non_ascii_key_uses_utf8() {
+	let user = LDUser::new("ユーザー".to_owned());
+	assert_eq!("369ab5375d72c085c5bc252c818521c26d1d016b890250b52664d0162e128ec9",
+		client("sdk-12345").secure_mode_hash(&user));
+}
+
+#[test]
+fn hash_of_other_kinds_uses_canonical_key() {
+	// the canonical key of an "org" context with key "o:1" is "org:o%3A1"
+	let context = LDContext::builder("org", "o:1".to_owned()).build().unwrap();
+	assert_eq!("6cd78a88eb23d480b90687f427b53d50db29139547fba63995ec6d0623a10b1e",
+		client("sdk-12345").context_secure_mode_hash(&context));
+}
+
+#[test]
+fn hash_depends_on_sdk_key() {
+	let user = LDUser::new("user-key".to_owned());
+	assert!(client("sdk-12345").secure_mode_hash(&user) != client("sdk-67890").secure_mode_hash(&user));
+}
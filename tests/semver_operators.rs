@@ -0,0 +1,112 @@
+
+// Table-driven tests for the semVerEqual, semVerLessThan and semVerGreaterThan clause operators,
+// which should accept the same versions as the other LaunchDarkly SDKs.
+
+extern crate ldclient;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use serde_json::Value;
+
+
+// Returns true if a user whose "version" attribute is user_value matches a clause with the
+// given operator and value.
+fn clause_matches(user_value: Value, op: &str, clause_value: &str) -> bool {
+	common::clause_matches("version", user_value, op, json!(clause_value))
+}
+
+fn check(cases: &[(&str, &str, &str, bool)]) {
+	for &(user_value, op, clause_value, expected) in cases {
+		assert_eq!(expected, clause_matches(json!(user_value), op, clause_value),
+			"{} {} {}", user_value, op, clause_value);
+	}
+}
+
+#[test]
+fn sem_ver_equal() {
+	check(&[
+		("2.0.0", "semVerEqual", "2.0.0", true),
+		("2.0.0", "semVerEqual", "2.0.1", false),
+		("2.0.0-rc.1", "semVerEqual", "2.0.0-rc.1", true),
+		("2.0.0-rc.1", "semVerEqual", "2.0.0", false),
+		("2.0.0-rc.1", "semVerEqual", "2.0.0-rc.2", false)
+	]);
+}
+
+#[test]
+fn minor_and_patch_versions_can_be_omitted() {
+	check(&[
+		("2", "semVerEqual", "2.0.0", true),
+		("2.0", "semVerEqual", "2.0.0", true),
+		("2.0.0", "semVerEqual", "2", true),
+		("2.1", "semVerEqual", "2.1.0", true),
+		("2.1", "semVerEqual", "2.0.0", false),
+		("2", "semVerLessThan", "2.0.1", true),
+		("2.1", "semVerGreaterThan", "2.0.5", true)
+	]);
+}
+
+#[test]
+fn prerelease_is_kept_when_minor_and_patch_are_omitted() {
+	check(&[
+		("2-rc1", "semVerEqual", "2.0.0-rc1", true),
+		("2.0-rc1", "semVerEqual", "2.0.0-rc1", true),
+		("2-rc1", "semVerEqual", "2.0.0", false),
+		("2-rc1", "semVerLessThan", "2.0.0", true),
+		("2.1-beta.2", "semVerGreaterThan", "2.1.0-beta.1", true)
+	]);
+}
+
+#[test]
+fn build_metadata_is_ignored() {
+	check(&[
+		("2.0.0+build1", "semVerEqual", "2.0.0", true),
+		("2.0.0+build1", "semVerEqual", "2.0.0+build2", true),
+		("2+build1", "semVerEqual", "2.0.0", true),
+		("2.0-rc1+build1", "semVerEqual", "2.0.0-rc1", true),
+		("2.0.0+build2", "semVerGreaterThan", "2.0.0+build1", false),
+		("2.0.0+build1", "semVerLessThan", "2.0.0+build2", false)
+	]);
+}
+
+#[test]
+fn sem_ver_less_than() {
+	check(&[
+		("2.0.0", "semVerLessThan", "2.0.1", true),
+		("2.0.1", "semVerLessThan", "2.0.0", false),
+		("2.0.0", "semVerLessThan", "2.0.0", false),
+		("1.9.9", "semVerLessThan", "2.0.0", true),
+		("2.0.0-rc", "semVerLessThan", "2.0.0", true),
+		("2.0.0-alpha", "semVerLessThan", "2.0.0-beta", true),
+		// numeric prerelease identifiers are compared as numbers
+		("2.0.0-rc.2", "semVerLessThan", "2.0.0-rc.10", true)
+	]);
+}
+
+#[test]
+fn sem_ver_greater_than() {
+	check(&[
+		("2.0.1", "semVerGreaterThan", "2.0.0", true),
+		("2.0.0", "semVerGreaterThan", "2.0.1", false),
+		("2.0.0", "semVerGreaterThan", "2.0.0", false),
+		("10.0.0", "semVerGreaterThan", "9.0.0", true),
+		("2.0.0", "semVerGreaterThan", "2.0.0-rc.1", true),
+		("2.0.0-rc.1", "semVerGreaterThan", "2.0.0-rc", true)
+	]);
+}
+
+#[test]
+fn invalid_versions_never_match() {
+	for &op in &["semVerEqual", "semVerLessThan", "semVerGreaterThan"] {
+		for &invalid in &["", "x", "2.", ".2", "2..0", "2.x", "v2.0.0", "2.0.0.0", "2.0.0-", "2-", "2+"] {
+			assert!(!clause_matches(json!(invalid), op, "3.0.0"), "{:?} {}", invalid, op);
+			assert!(!clause_matches(json!("3.0.0"), op, invalid), "{} {:?}", op, invalid);
+			assert!(!clause_matches(json!("1.0.0"), op, invalid), "{} {:?}", op, invalid);
+		}
+		// versions must be strings
+		assert!(!clause_matches(json!(2), op, "2.0.0"), "number {}", op);
+		assert!(!clause_matches(json!(true), op, "2.0.0"), "bool {}", op);
+	}
+}
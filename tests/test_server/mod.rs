@@ -0,0 +1,99 @@
+// A local HTTP server for tests of the client's network requests. Not every test file uses all of it.
+#![allow(dead_code)]
+
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::thread;
+use std::time::Duration;
+
+use flate2::read::GzDecoder;
+use serde_json;
+use serde_json::Value;
+use tiny_http::{Response, Server};
+
+
+// A request received by a TestServer.
+pub struct ReceivedRequest {
+	pub method: String,
+	pub path: String,
+	pub headers: Vec<(String, String)>,
+	pub body: Vec<u8>
+}
+
+impl ReceivedRequest {
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self.headers.iter().find(|&(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+	}
+
+	// The body as JSON, decompressing it first if it was gzipped.
+	pub fn json(&self) -> Value {
+		if self.header("Content-Encoding") == Some("gzip") {
+			let mut decompressed = vec![];
+			GzDecoder::new(&self.body[..]).read_to_end(&mut decompressed).unwrap();
+			serde_json::from_slice(&decompressed).unwrap()
+		} else {
+			serde_json::from_slice(&self.body).unwrap()
+		}
+	}
+}
+
+// A local HTTP server that records every request and answers it with the status and body that
+// the handler gives. It stops when dropped.
+pub struct TestServer {
+	pub uri: String,
+	server: Arc<Server>,
+	requests: Receiver<ReceivedRequest>
+}
+
+impl TestServer {
+	pub fn start<F>(handler: F) -> TestServer
+		where F: Fn(&ReceivedRequest) -> (u16, String) + Send + 'static {
+		let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+		let uri = format!("http://{}", server.server_addr());
+		let (tx, rx) = mpsc::channel();
+		let task_server = server.clone();
+		thread::spawn(move || {
+			for mut request in task_server.incoming_requests() {
+				let mut body = vec![];
+				request.as_reader().read_to_end(&mut body).unwrap();
+				let received = ReceivedRequest {
+					method: request.method().to_string(),
+					path: request.url().to_owned(),
+					headers: request.headers().iter()
+						.map(|h| (h.field.to_string(), h.value.to_string()))
+						.collect(),
+					body
+				};
+				let (status, response_body) = handler(&received);
+				// recorded before responding, so that it's there once the client has its response
+				let _ = tx.send(received);
+				let _ = request.respond(Response::from_string(response_body).with_status_code(status));
+			}
+		});
+		TestServer { uri, server, requests: rx }
+	}
+
+	// Answers each request with the next of the given statuses, then with 202.
+	pub fn with_statuses(statuses: Vec<u16>) -> TestServer {
+		let remaining = Mutex::new(statuses.into_iter());
+		TestServer::start(move |_| (remaining.lock().unwrap().next().unwrap_or(202), String::new()))
+	}
+
+	// Waits a few seconds for the next request.
+	pub fn next_request(&self) -> Option<ReceivedRequest> {
+		self.requests.recv_timeout(Duration::from_secs(5)).ok()
+	}
+
+	// The requests that have arrived so far, without waiting.
+	pub fn received(&self) -> Vec<ReceivedRequest> {
+		self.requests.try_iter().collect()
+	}
+}
+
+impl Drop for TestServer {
+	fn drop(&mut self) {
+		self.server.unblock();
+	}
+}
@@ -0,0 +1,97 @@
+// Tests for reading and writing users in the JSON format that the other LaunchDarkly SDKs use.
+
+extern crate ldclient;
+#[macro_use]
+extern crate serde_json;
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use ldclient::user::LDUser;
+
+
+fn full_user_json() -> Value {
+	json!({
+		"key": "user-key",
+		"secondary": "secondary-key",
+		"ip": "10.0.0.1",
+		"email": "test@example.com",
+		"name": "Test User",
+		"avatar": "https://example.com/avatar.png",
+		"firstName": "Test",
+		"lastName": "User",
+		"anonymous": false,
+		"country": "NZ",
+		"custom": {
+			"plan": "pro",
+			"seats": 12,
+			"beta": true,
+			"groups": ["a", "b"],
+			"address": { "city": "Oakland", "zip": ["94612", 94612] }
+		},
+		"privateAttributeNames": ["email", "address"]
+	})
+}
+
+#[test]
+fn full_user_round_trips() {
+	let user: LDUser = serde_json::from_value(full_user_json()).unwrap();
+	assert_eq!("user-key", user.key);
+	assert_eq!(Some("test@example.com".to_owned()), user.email);
+	assert_eq!(Some("Test".to_owned()), user.firstName);
+	assert_eq!(Some(false), user.anonymous);
+	let custom = user.custom.as_ref().unwrap();
+	assert_eq!(&json!({ "city": "Oakland", "zip": ["94612", 94612] }), &custom["address"]);
+	assert_eq!(Some(vec!["email".to_owned(), "address".to_owned()]), user.privateAttributeNames);
+	assert_eq!(full_user_json(), serde_json::to_value(&user).unwrap());
+	assert_eq!(user, serde_json::from_str(&serde_json::to_string(&user).unwrap()).unwrap());
+}
+
+#[test]
+fn unset_attributes_are_left_out() {
+	assert_eq!(json!({ "key": "user-key" }), serde_json::to_value(LDUser::new("user-key".to_owned())).unwrap());
+	let user = LDUser::builder("user-key".to_owned()).name("Name".to_owned()).build();
+	assert_eq!(json!({ "key": "user-key", "name": "Name" }), serde_json::to_value(&user).unwrap());
+}
+
+#[test]
+fn null_attributes_are_read_as_unset() {
+	let user: LDUser = serde_json::from_value(json!({
+		"key": "user-key",
+		"email": null,
+		"anonymous": null,
+		"custom": null,
+		"privateAttributeNames": null
+	})).unwrap();
+	assert_eq!(LDUser::new("user-key".to_owned()), user);
+}
+
+#[test]
+fn user_from_client_side_sdk_is_accepted() {
+	// as the JavaScript SDK sends it, with an anonymous key and fields that this SDK doesn't use
+	let user: LDUser = serde_json::from_value(json!({
+		"key": "f5c2bbd2-2e2f-4b5e-9c5e-4b8c9c7d2f5a",
+		"anonymous": true,
+		"custom": { "device": { "os": "iOS", "version": 17.1 } },
+		"privateAttributeNames": [],
+		"privateAttributes": ["device"],
+		"kind": "user"
+	})).unwrap();
+	assert_eq!(Some(true), user.anonymous);
+	let mut custom = HashMap::new();
+	custom.insert("device".to_owned(), json!({ "os": "iOS", "version": 17.1 }));
+	assert_eq!(Some(custom), user.custom);
+	assert_eq!(Some(vec![]), user.privateAttributeNames);
+	assert_eq!(json!({
+		"key": "f5c2bbd2-2e2f-4b5e-9c5e-4b8c9c7d2f5a",
+		"anonymous": true,
+		"custom": { "device": { "os": "iOS", "version": 17.1 } },
+		"privateAttributeNames": []
+	}), serde_json::to_value(&user).unwrap());
+}
+
+#[test]
+fn user_without_key_is_rejected() {
+	assert!(serde_json::from_value::<LDUser>(json!({ "name": "No Key" })).is_err());
+}
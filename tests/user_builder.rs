@@ -0,0 +1,69 @@
+// Tests for building users one attribute at a time with LDUserBuilder.
+
+extern crate ldclient;
+#[macro_use]
+extern crate serde_json;
+
+use std::f64;
+
+use serde_json::Value;
+
+use ldclient::user::LDUser;
+
+
+fn custom(user: &LDUser, name: &str) -> Value {
+	user.custom.as_ref().unwrap()[name].clone()
+}
+
+#[test]
+fn custom_number_stores_non_finite_values_as_null() {
+	let user = LDUser::builder("user-key".to_owned())
+		.custom_number("nan", f64::NAN)
+		.custom_number("inf", f64::INFINITY)
+		.custom_number("neg-inf", f64::NEG_INFINITY)
+		.custom_number("finite", 1.5)
+		.build();
+	assert_eq!(Value::Null, custom(&user, "nan"));
+	assert_eq!(Value::Null, custom(&user, "inf"));
+	assert_eq!(Value::Null, custom(&user, "neg-inf"));
+	assert_eq!(json!(1.5), custom(&user, "finite"));
+	// so the user can always be serialized
+	assert_eq!(json!(null), serde_json::to_value(&user).unwrap()["custom"]["nan"]);
+}
+
+#[test]
+fn custom_list_stores_an_array() {
+	let user = LDUser::builder("user-key".to_owned())
+		.custom_list("groups", vec!["a", "b"])
+		.custom_list("scores", vec![1, 2, 3])
+		.custom_list("empty", Vec::<String>::new())
+		.build();
+	assert_eq!(json!(["a", "b"]), custom(&user, "groups"));
+	assert_eq!(json!([1, 2, 3]), custom(&user, "scores"));
+	assert_eq!(json!([]), custom(&user, "empty"));
+}
+
+#[test]
+fn later_custom_value_replaces_earlier_one() {
+	let user = LDUser::builder("user-key".to_owned())
+		.custom_string("plan", "free")
+		.custom_bool("plan", true)
+		.build();
+	assert_eq!(json!(true), custom(&user, "plan"));
+	assert_eq!(1, user.custom.unwrap().len());
+}
+
+#[test]
+fn private_attribute_is_only_added_once() {
+	let user = LDUser::builder("user-key".to_owned())
+		.private_attribute("email")
+		.private_attribute("team")
+		.private_attribute("email")
+		.build();
+	assert_eq!(Some(vec!["email".to_owned(), "team".to_owned()]), user.privateAttributeNames);
+}
+
+#[test]
+fn unset_builder_gives_plain_user() {
+	assert_eq!(LDUser::new("user-key".to_owned()), LDUser::builder("user-key".to_owned()).build());
+}
@@ -0,0 +1,171 @@
+// Tests for the user and context JSON that goes into analytics events, which must not contain
+// private attributes.
+
+#[macro_use]
+extern crate serde_json;
+extern crate ldclient;
+
+use ldclient::config::LDConfig;
+use ldclient::context::LDContext;
+use ldclient::user::LDUser;
+use ldclient::user_filter::UserFilter;
+
+
+fn user() -> LDUser {
+	LDUser::builder("user-key".to_owned())
+		.email("test@example.com".to_owned())
+		.name("Test User".to_owned())
+		.anonymous(true)
+		.custom_string("team", "a")
+		.custom("address", json!({ "street": "1 Main St", "city": "Oakland", "geo": { "lat": 1, "long": 2 } }))
+		.build()
+}
+
+#[test]
+fn nothing_private_by_default() {
+	let filter = UserFilter::new(&LDConfig::default());
+	assert_eq!(json!({
+		"key": "user-key",
+		"email": "test@example.com",
+		"name": "Test User",
+		"anonymous": true,
+		"custom": {
+			"team": "a",
+			"address": { "street": "1 Main St", "city": "Oakland", "geo": { "lat": 1, "long": 2 } }
+		}
+	}), filter.filter_user(&user()));
+}
+
+#[test]
+fn all_attributes_private_keeps_key_and_anonymous() {
+	let filter = UserFilter::new(&LDConfig::default().with_all_attributes_private(true));
+	assert_eq!(json!({
+		"key": "user-key",
+		"anonymous": true,
+		"privateAttrs": ["address", "email", "name", "team"]
+	}), filter.filter_user(&user()));
+}
+
+#[test]
+fn global_private_attributes() {
+	let config = LDConfig::default().with_private_attribute_names(vec!["email".to_owned(), "team".to_owned()]);
+	let filter = UserFilter::new(&config);
+	assert_eq!(json!({
+		"key": "user-key",
+		"name": "Test User",
+		"anonymous": true,
+		"custom": {
+			"address": { "street": "1 Main St", "city": "Oakland", "geo": { "lat": 1, "long": 2 } }
+		},
+		"privateAttrs": ["email", "team"]
+	}), filter.filter_user(&user()));
+}
+
+#[test]
+fn per_user_private_attributes() {
+	let u = LDUser::builder("user-key".to_owned())
+		.email("test@example.com".to_owned())
+		.name("Test User".to_owned())
+		.private_attribute("name")
+		.build();
+	let filter = UserFilter::new(&LDConfig::default());
+	assert_eq!(json!({
+		"key": "user-key",
+		"email": "test@example.com",
+		"privateAttrs": ["name"]
+	}), filter.filter_user(&u));
+}
+
+#[test]
+fn attribute_private_in_both_places_is_listed_once() {
+	let u = LDUser::builder("user-key".to_owned())
+		.email("test@example.com".to_owned())
+		.private_attribute("email")
+		.build();
+	let filter = UserFilter::new(&LDConfig::default().with_private_attribute_names(vec!["email".to_owned()]));
+	assert_eq!(json!({ "key": "user-key", "privateAttrs": ["email"] }), filter.filter_user(&u));
+}
+
+#[test]
+fn nested_refs_remove_only_the_nested_property() {
+	let config = LDConfig::default()
+		.with_private_attribute_names(vec!["/address/street".to_owned(), "/address/geo/lat".to_owned()]);
+	let filter = UserFilter::new(&config);
+	let out = filter.filter_user(&user());
+	assert_eq!(json!({ "city": "Oakland", "geo": { "long": 2 } }), out["custom"]["address"]);
+	assert_eq!(json!(["/address/street", "/address/geo/lat"]), out["privateAttrs"]);
+}
+
+#[test]
+fn nested_refs_that_match_nothing_are_not_listed() {
+	let config = LDConfig::default()
+		.with_private_attribute_names(vec!["/address/zip".to_owned(), "/team/x".to_owned(), "/missing/x".to_owned()]);
+	let filter = UserFilter::new(&config);
+	let out = filter.filter_user(&user());
+	assert_eq!("a", out["custom"]["team"]);
+	assert_eq!(3, out["custom"]["address"].as_object().unwrap().len());
+	assert!(out.get("privateAttrs").is_none());
+}
+
+#[test]
+fn single_component_ref_removes_whole_attribute() {
+	let config = LDConfig::default()
+		.with_private_attribute_names(vec!["/address".to_owned(), "/address/street".to_owned()]);
+	let filter = UserFilter::new(&config);
+	let out = filter.filter_user(&user());
+	assert!(out["custom"].get("address").is_none());
+	assert_eq!(json!(["/address"]), out["privateAttrs"]);
+}
+
+#[test]
+fn non_user_context_lists_redacted_attributes_in_meta() {
+	let context = LDContext::builder("org", "org-key".to_owned())
+		.name("Org".to_owned())
+		.set("plan", json!("gold"))
+		.set("billing", json!({ "email": "billing@example.com", "country": "NZ" }))
+		.private_attribute("plan")
+		.private_attribute("/billing/email")
+		.build().unwrap();
+	let filter = UserFilter::new(&LDConfig::default());
+	assert_eq!(json!({
+		"kind": "org",
+		"key": "org-key",
+		"name": "Org",
+		"billing": { "country": "NZ" },
+		"_meta": { "redactedAttributes": ["/billing/email", "plan"] }
+	}), filter.filter_context(&context));
+}
+
+#[test]
+fn non_user_context_with_all_attributes_private() {
+	let context = LDContext::builder("org", "org-key".to_owned())
+		.name("Org".to_owned())
+		.anonymous(true)
+		.build().unwrap();
+	let filter = UserFilter::new(&LDConfig::default().with_all_attributes_private(true));
+	assert_eq!(json!({
+		"kind": "org",
+		"key": "org-key",
+		"anonymous": true,
+		"_meta": { "redactedAttributes": ["name"] }
+	}), filter.filter_context(&context));
+}
+
+#[test]
+fn multi_kind_context_redacts_each_kind() {
+	let org = LDContext::builder("org", "org-key".to_owned())
+		.name("Org".to_owned())
+		.private_attribute("name")
+		.build().unwrap();
+	let device = LDContext::builder("device", "device-key".to_owned())
+		.name("Phone".to_owned())
+		.set("os", json!("linux"))
+		.build().unwrap();
+	let context = LDContext::multi(vec![org, device]).unwrap();
+	let filter = UserFilter::new(&LDConfig::default().with_private_attribute_names(vec!["os".to_owned()]));
+	assert_eq!(json!({
+		"kind": "multi",
+		"org": { "key": "org-key", "_meta": { "redactedAttributes": ["name"] } },
+		"device": { "key": "device-key", "name": "Phone", "_meta": { "redactedAttributes": ["os"] } }
+	}), filter.filter_context(&context));
+}